@@ -0,0 +1,76 @@
+/// Reading of the little endian binary files the abstractions, blueprints and
+/// checkpoints are saved in. Errors name the kind of file being read, and a
+/// truncated file is `InvalidData` like any other corrupt one.
+use std::io::{Error, ErrorKind, Result};
+
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    file_kind: &'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8], file_kind: &'static str) -> Self {
+        ByteReader { bytes, position: 0, file_kind }
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    pub fn n_remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    pub fn take(&mut self, n_bytes: usize) -> Result<&'a [u8]> {
+        if n_bytes > self.n_remaining() {
+            return Err(Error::new(ErrorKind::InvalidData, format!("{} file is truncated", self.file_kind)));
+        }
+        let end = self.position + n_bytes;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    pub fn expect_header(&mut self, magic: &[u8; 4], version: u32) -> Result<()> {
+        // Every file starts with four magic bytes and a u32 version.
+        if self.take(4)? != magic {
+            return Err(Error::new(ErrorKind::InvalidData, format!("Not a {} file", self.file_kind.to_lowercase())));
+        }
+        let file_version = self.u32()?;
+        if file_version != version {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported {} version {}, expected {}", self.file_kind.to_lowercase(), file_version, version),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+pub fn temp_path(name: &str) -> String {
+    // A file in the temporary directory unique to this test process.
+    let file_name = format!("pluribus-{}-{}", std::process::id(), name);
+    std::env::temp_dir().join(file_name).to_str().unwrap().to_string()
+}
@@ -0,0 +1,7 @@
+pub mod agent;
+pub mod best_response;
+pub mod game_state;
+pub mod mccfr;
+pub mod parallel_trainer;
+pub mod search;
+pub mod trainer;
@@ -0,0 +1,252 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::io::{Error, ErrorKind, Result};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::bytes::ByteReader;
+
+/// What a CFR traversal reads from and writes to the regret and cumulative
+/// strategy tables. `Agent` is the single threaded implementation, and a
+/// shared reference to a `ShardedAgent` lets several threads traverse at once.
+pub trait StrategyTables<I> {
+    fn current_strategy(&self, info_set: &I, n_actions: usize) -> Vec<f64>;
+
+    // The regrets of an information set, or None if it was never updated.
+    fn regrets(&self, info_set: &I) -> Option<Vec<f64>>;
+
+    fn add_regrets(&mut self, info_set: I, regrets: &[f64]);
+
+    fn add_strategy(&mut self, info_set: I, sigma: &[f64]);
+}
+
+/// Regret and cumulative strategy tables, keyed by information set. Each entry
+/// holds one value per legal action, in the order given by
+/// `GameState::legal_actions`.
+pub struct Agent<I: Eq + Hash> {
+    pub regret: HashMap<I, Vec<f64>>,
+    pub strategy: HashMap<I, Vec<f64>>,
+}
+
+impl<I: Eq + Hash> Agent<I> {
+    pub fn new() -> Self {
+        Agent {
+            regret: HashMap::new(),
+            strategy: HashMap::new(),
+        }
+    }
+
+    pub fn current_strategy(&self, info_set: &I, n_actions: usize) -> Vec<f64> {
+        regret_matching(self.regret.get(info_set), n_actions)
+    }
+
+    pub fn average_strategy(&self, info_set: &I, n_actions: usize) -> Vec<f64> {
+        // The average strategy, not the current one, converges to equilibrium.
+        match self.strategy.get(info_set) {
+            Some(strategy_sum) => normalise(strategy_sum.clone()),
+            None => vec![1.0 / n_actions as f64; n_actions],
+        }
+    }
+
+    pub fn discount(&mut self, d: f64) {
+        // Scale both tables down, so that later iterations outweigh earlier
+        // ones as in linear CFR.
+        for values in self.regret.values_mut().chain(self.strategy.values_mut()) {
+            values.iter_mut().for_each(|v| *v *= d);
+        }
+    }
+
+    pub fn n_info_sets(&self) -> usize {
+        self.regret.len().max(self.strategy.len())
+    }
+}
+
+impl<I: Eq + Hash> StrategyTables<I> for Agent<I> {
+    fn current_strategy(&self, info_set: &I, n_actions: usize) -> Vec<f64> {
+        Agent::current_strategy(self, info_set, n_actions)
+    }
+
+    fn regrets(&self, info_set: &I) -> Option<Vec<f64>> {
+        self.regret.get(info_set).cloned()
+    }
+
+    fn add_regrets(&mut self, info_set: I, regrets: &[f64]) {
+        add_to(&mut self.regret, info_set, regrets);
+    }
+
+    fn add_strategy(&mut self, info_set: I, sigma: &[f64]) {
+        add_to(&mut self.strategy, info_set, sigma);
+    }
+}
+
+impl<I: Eq + Hash + AsRef<[u8]> + for<'a> From<&'a [u8]>> Agent<I> {
+    pub fn write_tables(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        write_table(bytes, self.regret.len(), self.regret.iter())?;
+        write_table(bytes, self.strategy.len(), self.strategy.iter())
+    }
+
+    pub fn read_tables(reader: &mut ByteReader) -> Result<Self> {
+        Ok(Agent {
+            regret: read_table(reader)?,
+            strategy: read_table(reader)?,
+        })
+    }
+}
+
+impl<I: Eq + Hash> Default for Agent<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Regret and cumulative strategy tables shared between training threads.
+/// Information sets are spread over shards by hash, each behind its own lock,
+/// so threads only wait on each other when they touch the same shard.
+pub struct ShardedAgent<I: Eq + Hash> {
+    shards: Vec<Mutex<Agent<I>>>,
+    hasher: RandomState,
+}
+
+impl<I: Eq + Hash> ShardedAgent<I> {
+    pub fn new(n_shards: usize) -> Self {
+        ShardedAgent::from_agent(Agent::new(), n_shards)
+    }
+
+    pub fn from_agent(agent: Agent<I>, n_shards: usize) -> Self {
+        let sharded = ShardedAgent {
+            shards: (0..n_shards).map(|_| Mutex::new(Agent::new())).collect(),
+            hasher: RandomState::new(),
+        };
+        for (info_set, regrets) in agent.regret {
+            sharded.shard(&info_set).regret.insert(info_set, regrets);
+        }
+        for (info_set, strategy_sum) in agent.strategy {
+            sharded.shard(&info_set).strategy.insert(info_set, strategy_sum);
+        }
+        sharded
+    }
+
+    pub fn into_agent(self) -> Agent<I> {
+        let mut agent = Agent::new();
+        for shard in self.shards {
+            let shard = shard.into_inner().unwrap();
+            agent.regret.extend(shard.regret);
+            agent.strategy.extend(shard.strategy);
+        }
+        agent
+    }
+
+    pub fn average_strategy(&self, info_set: &I, n_actions: usize) -> Vec<f64> {
+        self.shard(info_set).average_strategy(info_set, n_actions)
+    }
+
+    pub fn discount(&self, d: f64) {
+        for shard in &self.shards {
+            shard.lock().unwrap().discount(d);
+        }
+    }
+
+    pub fn n_info_sets(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().n_info_sets()).sum()
+    }
+
+    fn shard(&self, info_set: &I) -> MutexGuard<'_, Agent<I>> {
+        let shard_i = self.hasher.hash_one(info_set) as usize % self.shards.len();
+        self.shards[shard_i].lock().unwrap()
+    }
+}
+
+impl<I: Eq + Hash + AsRef<[u8]> + for<'a> From<&'a [u8]>> ShardedAgent<I> {
+    pub fn write_tables(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        // The same layout as `Agent::write_tables`, so checkpoints can be
+        // resumed with either.
+        let shards: Vec<MutexGuard<'_, Agent<I>>> = self.shards.iter().map(|shard| shard.lock().unwrap()).collect();
+        let n_regrets = shards.iter().map(|shard| shard.regret.len()).sum();
+        write_table(bytes, n_regrets, shards.iter().flat_map(|shard| shard.regret.iter()))?;
+        let n_strategies = shards.iter().map(|shard| shard.strategy.len()).sum();
+        write_table(bytes, n_strategies, shards.iter().flat_map(|shard| shard.strategy.iter()))
+    }
+}
+
+impl<I: Eq + Hash> StrategyTables<I> for &ShardedAgent<I> {
+    fn current_strategy(&self, info_set: &I, n_actions: usize) -> Vec<f64> {
+        self.shard(info_set).current_strategy(info_set, n_actions)
+    }
+
+    fn regrets(&self, info_set: &I) -> Option<Vec<f64>> {
+        self.shard(info_set).regret.get(info_set).cloned()
+    }
+
+    fn add_regrets(&mut self, info_set: I, regrets: &[f64]) {
+        add_to(&mut self.shard(&info_set).regret, info_set, regrets);
+    }
+
+    fn add_strategy(&mut self, info_set: I, sigma: &[f64]) {
+        add_to(&mut self.shard(&info_set).strategy, info_set, sigma);
+    }
+}
+
+fn regret_matching(regrets: Option<&Vec<f64>>, n_actions: usize) -> Vec<f64> {
+    // Play actions in proportion to their positive regret, or uniformly if no
+    // action has any.
+    match regrets {
+        Some(regrets) => normalise(regrets.iter().map(|r| r.max(0.0)).collect()),
+        None => vec![1.0 / n_actions as f64; n_actions],
+    }
+}
+
+fn normalise(mut values: Vec<f64>) -> Vec<f64> {
+    let total: f64 = values.iter().sum();
+    if total > 0.0 {
+        values.iter_mut().for_each(|v| *v /= total);
+    } else {
+        let n_actions = values.len() as f64;
+        values.iter_mut().for_each(|v| *v = 1.0 / n_actions);
+    }
+    values
+}
+
+fn add_to<I: Eq + Hash>(table: &mut HashMap<I, Vec<f64>>, info_set: I, values: &[f64]) {
+    let totals = table.entry(info_set).or_insert_with(|| vec![0.0; values.len()]);
+    for (total, value) in totals.iter_mut().zip(values) {
+        *total += value;
+    }
+}
+
+fn write_table<'a, I: AsRef<[u8]> + 'a>(
+    bytes: &mut Vec<u8>,
+    n_info_sets: usize,
+    table: impl Iterator<Item = (&'a I, &'a Vec<f64>)>,
+) -> Result<()> {
+    // n_info_sets: u64, then per info set its key length: u16, the key bytes,
+    // n_actions: u8 and the values as f64, all little endian.
+    bytes.extend_from_slice(&(n_info_sets as u64).to_le_bytes());
+    for (info_set, values) in table {
+        let key = info_set.as_ref();
+        if key.len() > u16::MAX as usize || values.len() > u8::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "Info set too large for a checkpoint"));
+        }
+        bytes.extend_from_slice(&(key.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(key);
+        bytes.push(values.len() as u8);
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn read_table<I: Eq + Hash + for<'a> From<&'a [u8]>>(reader: &mut ByteReader) -> Result<HashMap<I, Vec<f64>>> {
+    // Every info set takes at least three bytes, which bounds how much a
+    // corrupt count can make us preallocate.
+    let n_info_sets = reader.u64()? as usize;
+    let mut table = HashMap::with_capacity(n_info_sets.min(reader.n_remaining() / 3));
+    for _ in 0..n_info_sets {
+        let key_len = reader.u16()? as usize;
+        let info_set = I::from(reader.take(key_len)?);
+        let n_actions = reader.u8()? as usize;
+        let values = (0..n_actions).map(|_| reader.f64()).collect::<Result<Vec<f64>>>()?;
+        table.insert(info_set, values);
+    }
+    Ok(table)
+}
@@ -0,0 +1,263 @@
+/// Exact best responses and exploitability, for games small enough to expand
+/// in full: a reduced deck through `Deck::new` and an action abstraction with
+/// few bet sizes.
+///
+/// The tree is built once with the policy's action probabilities at every
+/// decision node. A best responder for player i knows the policy but not the
+/// other players' cards, so at each of their information sets they pick the
+/// action with the highest value summed over the histories in it, weighted by
+/// how likely chance and the other players are to reach each one.
+///
+/// A policy is any function from a decision node to probabilities over its
+/// legal actions, such as a trained agent's average strategy:
+/// ```text
+/// exploitability(&root, |state| agent.average_strategy(&state.info_set(), state.legal_actions().len()))
+/// ```
+use std::collections::HashMap;
+
+use super::game_state::ChanceOutcomes;
+
+enum Node<I> {
+    Terminal {
+        payouts: Vec<f64>,
+    },
+    Chance {
+        children: Vec<(usize, f64)>,
+    },
+    Decision {
+        player_i: usize,
+        info_set: I,
+        children: Vec<usize>,
+        sigma: Vec<f64>,
+    },
+}
+
+/// How far a policy is from equilibrium, in chips per game. Exploitability is
+/// the average over players of how much more they win by best responding.
+/// In a two player zero sum game that is the mean of the two best response
+/// values, and zero exactly at a Nash equilibrium.
+pub struct Exploitability {
+    pub policy_values: Vec<f64>,
+    pub best_response_values: Vec<f64>,
+}
+
+impl Exploitability {
+    pub fn nash_conv(&self) -> f64 {
+        self.best_response_values
+            .iter()
+            .zip(&self.policy_values)
+            .map(|(best_response_value, policy_value)| best_response_value - policy_value)
+            .sum()
+    }
+
+    pub fn exploitability(&self) -> f64 {
+        self.nash_conv() / self.policy_values.len() as f64
+    }
+
+    pub fn mbb_per_game(&self, big_blind: f64) -> f64 {
+        // Milli big blinds per game, the usual unit for poker.
+        1000.0 * self.exploitability() / big_blind
+    }
+}
+
+/// The full game tree under a fixed policy. Nodes are stored in depth first
+/// order, so every node comes before its children.
+pub struct GameTree<I> {
+    nodes: Vec<Node<I>>,
+    n_players: usize,
+}
+
+impl<I: Clone + Eq + std::hash::Hash> GameTree<I> {
+    pub fn new<S, P>(root: &S, policy: P) -> Self
+    where
+        S: ChanceOutcomes<InfoSet = I>,
+        P: Fn(&S) -> Vec<f64>,
+    {
+        let mut tree = GameTree {
+            nodes: Vec::new(),
+            n_players: root.n_players(),
+        };
+        tree.add(root, &policy);
+        tree
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn policy_values(&self) -> Vec<f64> {
+        // Expected payouts when everyone follows the policy. Children come
+        // after their parents, so walking backwards values children first.
+        let mut values: Vec<Vec<f64>> = vec![Vec::new(); self.nodes.len()];
+        for node_i in (0..self.nodes.len()).rev() {
+            values[node_i] = match &self.nodes[node_i] {
+                Node::Terminal { payouts } => payouts.clone(),
+                Node::Chance { children } => self.weighted_sum(&values, children.iter().copied()),
+                Node::Decision { children, sigma, .. } => {
+                    self.weighted_sum(&values, children.iter().copied().zip(sigma.iter().copied()))
+                }
+            };
+        }
+        values.swap_remove(0)
+    }
+
+    pub fn best_response_value(&self, player_i: usize) -> f64 {
+        // The probability that chance and the other players reach each node.
+        let mut reach = vec![0.0; self.nodes.len()];
+        reach[0] = 1.0;
+        let mut info_sets: HashMap<&I, Vec<usize>> = HashMap::new();
+        for (node_i, node) in self.nodes.iter().enumerate() {
+            match node {
+                Node::Terminal { .. } => {}
+                Node::Chance { children } => {
+                    for &(child_i, probability) in children {
+                        reach[child_i] = reach[node_i] * probability;
+                    }
+                }
+                Node::Decision { player_i: acting_i, info_set, children, sigma } => {
+                    for (&child_i, &probability) in children.iter().zip(sigma) {
+                        reach[child_i] = if *acting_i == player_i { reach[node_i] } else { reach[node_i] * probability };
+                    }
+                    if *acting_i == player_i {
+                        info_sets.entry(info_set).or_default().push(node_i);
+                    }
+                }
+            }
+        }
+        let mut best_response = BestResponse {
+            tree: self,
+            player_i,
+            reach,
+            info_sets,
+            best_actions: HashMap::new(),
+            values: vec![None; self.nodes.len()],
+        };
+        best_response.value(0)
+    }
+
+    pub fn exploitability(&self) -> Exploitability {
+        Exploitability {
+            policy_values: self.policy_values(),
+            best_response_values: (0..self.n_players).map(|player_i| self.best_response_value(player_i)).collect(),
+        }
+    }
+
+    fn add<S, P>(&mut self, state: &S, policy: &P) -> usize
+    where
+        S: ChanceOutcomes<InfoSet = I>,
+        P: Fn(&S) -> Vec<f64>,
+    {
+        // Reserves this node's index before adding its children after it.
+        let node_i = self.nodes.len();
+        self.nodes.push(Node::Terminal { payouts: Vec::new() });
+        let node = if state.is_terminal() {
+            Node::Terminal {
+                payouts: (0..self.n_players).map(|player_i| state.payout(player_i)).collect(),
+            }
+        } else if state.is_chance_node() {
+            let children = state
+                .chance_outcomes()
+                .iter()
+                .map(|(outcome, probability)| (self.add(outcome, policy), *probability))
+                .collect();
+            Node::Chance { children }
+        } else {
+            let actions = state.legal_actions();
+            let sigma = policy(state);
+            if sigma.len() != actions.len() {
+                panic!("The policy gave {} probabilities for {} actions", sigma.len(), actions.len());
+            }
+            let children = actions
+                .iter()
+                .map(|action| self.add(&state.apply_action(action), policy))
+                .collect();
+            Node::Decision {
+                player_i: state.player_i(),
+                info_set: state.info_set(),
+                children,
+                sigma,
+            }
+        };
+        self.nodes[node_i] = node;
+        node_i
+    }
+
+    fn weighted_sum(&self, values: &[Vec<f64>], children: impl Iterator<Item = (usize, f64)>) -> Vec<f64> {
+        let mut total = vec![0.0; self.n_players];
+        for (child_i, probability) in children {
+            for (total, value) in total.iter_mut().zip(&values[child_i]) {
+                *total += probability * value;
+            }
+        }
+        total
+    }
+}
+
+/// The state of one best response computation. Node values and the best
+/// action of each information set are memoized, as every history of an
+/// information set needs the values below all of its actions.
+struct BestResponse<'a, I> {
+    tree: &'a GameTree<I>,
+    player_i: usize,
+    reach: Vec<f64>,
+    info_sets: HashMap<&'a I, Vec<usize>>,
+    best_actions: HashMap<&'a I, usize>,
+    values: Vec<Option<f64>>,
+}
+
+impl<'a, I: Clone + Eq + std::hash::Hash> BestResponse<'a, I> {
+    fn value(&mut self, node_i: usize) -> f64 {
+        if let Some(value) = self.values[node_i] {
+            return value;
+        }
+        let tree = self.tree;
+        let value = match &tree.nodes[node_i] {
+            Node::Terminal { payouts } => payouts[self.player_i],
+            Node::Chance { children } => children
+                .iter()
+                .map(|&(child_i, probability)| probability * self.value(child_i))
+                .sum(),
+            Node::Decision { player_i, info_set, children, .. } if *player_i == self.player_i => {
+                let action_i = self.best_action(info_set);
+                self.value(children[action_i])
+            }
+            Node::Decision { children, sigma, .. } => children
+                .iter()
+                .zip(sigma)
+                .map(|(&child_i, &probability)| probability * self.value(child_i))
+                .sum(),
+        };
+        self.values[node_i] = Some(value);
+        value
+    }
+
+    fn best_action(&mut self, info_set: &'a I) -> usize {
+        if let Some(&action_i) = self.best_actions.get(info_set) {
+            return action_i;
+        }
+        let tree = self.tree;
+        let node_is = self.info_sets[info_set].clone();
+        let mut action_values = Vec::new();
+        for node_i in node_is {
+            if let Node::Decision { children, .. } = &tree.nodes[node_i] {
+                action_values.resize(children.len(), 0.0);
+                for (action_i, &child_i) in children.iter().enumerate() {
+                    action_values[action_i] += self.reach[node_i] * self.value(child_i);
+                }
+            }
+        }
+        let action_i = (0..action_values.len())
+            .max_by(|&a, &b| action_values[a].partial_cmp(&action_values[b]).unwrap())
+            .unwrap();
+        self.best_actions.insert(info_set, action_i);
+        action_i
+    }
+}
+
+pub fn exploitability<S, P>(root: &S, policy: P) -> Exploitability
+where
+    S: ChanceOutcomes,
+    P: Fn(&S) -> Vec<f64>,
+{
+    GameTree::new(root, policy).exploitability()
+}
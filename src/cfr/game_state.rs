@@ -0,0 +1,40 @@
+/// The view of a game tree that the CFR trainer walks. A state is one node of
+/// the tree: applying an action or sampling a chance outcome returns the child
+/// node and leaves the parent untouched, so the trainer can explore several
+/// branches from the same state.
+use std::fmt::Debug;
+use std::hash::Hash;
+
+pub trait GameState: Sized {
+    type Action: Clone + Eq + Hash + Debug;
+    type InfoSet: Clone + Eq + Hash;
+
+    fn n_players(&self) -> usize;
+
+    fn is_terminal(&self) -> bool;
+
+    fn is_chance_node(&self) -> bool;
+
+    // Index of the player to act, only meaningful on decision nodes.
+    fn player_i(&self) -> usize;
+
+    // Legal actions in a fixed order, so that every state in an information
+    // set lists them identically.
+    fn legal_actions(&self) -> Vec<Self::Action>;
+
+    fn apply_action(&self, action: &Self::Action) -> Self;
+
+    fn sample_chance_outcome(&self) -> Self;
+
+    // Chips won or lost by `player_i`, only meaningful on terminal nodes.
+    fn payout(&self, player_i: usize) -> f64;
+
+    fn info_set(&self) -> Self::InfoSet;
+}
+
+/// Games whose chance nodes can be enumerated, for exact computations over
+/// the whole tree such as best responses.
+pub trait ChanceOutcomes: GameState {
+    // Every child of a chance node with its probability.
+    fn chance_outcomes(&self) -> Vec<(Self, f64)>;
+}
@@ -0,0 +1,66 @@
+/// External-sampling Monte Carlo CFR, as in Lanctot et al. (2009). Each
+/// traversal updates the regrets of one player: every action of the traverser
+/// is explored, while chance and the opponents are sampled once per node.
+/// Opponent nodes also accumulate the current strategy into the average
+/// strategy, which is what converges to an equilibrium.
+///
+/// With `prune_threshold` set, the traverser skips actions whose regret has
+/// fallen below it, as Pluribus does once training has settled.
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::thread_rng;
+
+use super::agent::StrategyTables;
+use super::game_state::GameState;
+
+pub fn cfr<S: GameState, T: StrategyTables<S::InfoSet>>(
+    agent: &mut T,
+    state: &S,
+    i: usize,
+    prune_threshold: Option<f64>,
+) -> f64 {
+    if state.is_terminal() {
+        return state.payout(i);
+    }
+    if state.is_chance_node() {
+        return cfr(agent, &state.sample_chance_outcome(), i, prune_threshold);
+    }
+
+    let info_set = state.info_set();
+    let actions = state.legal_actions();
+    let sigma = agent.current_strategy(&info_set, actions.len());
+
+    if state.player_i() == i {
+        let explored: Vec<bool> = match (prune_threshold, agent.regrets(&info_set)) {
+            (Some(threshold), Some(regrets)) => regrets.iter().map(|&r| r > threshold).collect(),
+            _ => vec![true; actions.len()],
+        };
+        let mut action_values = vec![0.0; actions.len()];
+        let mut value = 0.0;
+        for (a_i, action) in actions.iter().enumerate() {
+            if explored[a_i] {
+                action_values[a_i] = cfr(agent, &state.apply_action(action), i, prune_threshold);
+                value += sigma[a_i] * action_values[a_i];
+            }
+        }
+        let regrets: Vec<f64> = action_values
+            .iter()
+            .zip(&explored)
+            .map(|(action_value, &explored)| if explored { action_value - value } else { 0.0 })
+            .collect();
+        agent.add_regrets(info_set, &regrets);
+        value
+    } else {
+        agent.add_strategy(info_set, &sigma);
+        let a_i = sample_action(&sigma);
+        cfr(agent, &state.apply_action(&actions[a_i]), i, prune_threshold)
+    }
+}
+
+pub fn sample_action(sigma: &[f64]) -> usize {
+    match WeightedIndex::new(sigma) {
+        Ok(distribution) => distribution.sample(&mut thread_rng()),
+        // All weights zero can only come from a degenerate strategy, so fall
+        // back to the first action rather than panicking mid-traversal.
+        Err(_) => 0,
+    }
+}
@@ -0,0 +1,205 @@
+use std::io::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::agent::{Agent, ShardedAgent};
+use super::game_state::GameState;
+use super::mccfr;
+use super::trainer::{read_checkpoint, write_checkpoint, Progress, TrainerConfig};
+
+/// Runs MCCFR iterations on several threads at once, all updating one
+/// `ShardedAgent`. Each thread builds its own roots with the supplied closure,
+/// so game states never cross threads and only the information sets need to
+/// be `Send + Sync`.
+///
+/// The threads meet whenever the tables are discounted, progress is printed
+/// or a checkpoint is due, so those happen at the same iterations as with
+/// `Trainer`. Within a batch iterations run concurrently, so a traversal may
+/// see regrets that other threads are still updating, as in other parallel
+/// MCCFR implementations.
+pub struct ParallelTrainer<S: GameState> {
+    pub agent: ShardedAgent<S::InfoSet>,
+    config: TrainerConfig,
+    n_threads: usize,
+    iteration: usize,
+}
+
+impl<S: GameState> ParallelTrainer<S>
+where
+    S::InfoSet: Send + Sync,
+{
+    // Enough shards per thread that threads rarely wait on the same lock.
+    const SHARDS_PER_THREAD: usize = 16;
+
+    pub fn new(config: TrainerConfig, n_threads: usize) -> Self {
+        ParallelTrainer {
+            agent: ShardedAgent::new(n_threads * Self::SHARDS_PER_THREAD),
+            config,
+            n_threads,
+            iteration: 0,
+        }
+    }
+
+    pub fn with_all_cores(config: TrainerConfig) -> Self {
+        let n_threads = thread::available_parallelism().map_or(1, |n| n.get());
+        ParallelTrainer::new(config, n_threads)
+    }
+
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    pub fn n_threads(&self) -> usize {
+        self.n_threads
+    }
+
+    pub fn into_agent(self) -> Agent<S::InfoSet> {
+        self.agent.into_agent()
+    }
+
+    pub fn train<F: Fn() -> S + Sync>(&mut self, new_root: F) {
+        let progress = Progress::start(self.iteration);
+        while self.iteration < self.config.n_iterations {
+            self.run_batch(&new_root, &progress);
+        }
+    }
+
+    fn run_batch<F: Fn() -> S + Sync>(&mut self, new_root: &F, progress: &Progress) {
+        // Iterations up to the next stop are handed out to the threads one at
+        // a time, then the tables are discounted and progress printed.
+        let end = self.next_stop();
+        let next_iteration = AtomicUsize::new(self.iteration + 1);
+        let agent = &self.agent;
+        let config = &self.config;
+        thread::scope(|scope| {
+            for _ in 0..self.n_threads {
+                scope.spawn(|| {
+                    let mut tables = agent;
+                    loop {
+                        let iteration = next_iteration.fetch_add(1, Ordering::Relaxed);
+                        if iteration > end {
+                            break;
+                        }
+                        let root = new_root();
+                        for i in 0..root.n_players() {
+                            mccfr::cfr(&mut tables, &root, i, config.prune_threshold(iteration));
+                        }
+                    }
+                });
+            }
+        });
+        self.iteration = end;
+        if let Some(d) = self.config.discount(self.iteration) {
+            self.agent.discount(d);
+        }
+        if self.iteration.is_multiple_of(self.config.print_iteration) {
+            progress.print(self.iteration, self.agent.n_info_sets());
+        }
+    }
+
+    fn next_stop(&self) -> usize {
+        // The first iteration after the current one at which the tables are
+        // discounted, progress is printed, a checkpoint is due or training
+        // ends.
+        let next_multiple = |interval: usize| match interval {
+            0 => usize::MAX,
+            _ => (self.iteration / interval + 1) * interval,
+        };
+        let mut stop = self.config.n_iterations
+            .min(next_multiple(self.config.print_iteration))
+            .min(next_multiple(self.config.checkpoint_iteration));
+        if self.iteration + 1 < self.config.lcfr_threshold {
+            stop = stop.min(next_multiple(self.config.discount_interval));
+        }
+        stop
+    }
+}
+
+impl<S: GameState> ParallelTrainer<S>
+where
+    S::InfoSet: Send + Sync + AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    pub fn resume(config: TrainerConfig, n_threads: usize, path: &str) -> Result<Self> {
+        let (iteration, agent) = read_checkpoint(path, Agent::read_tables)?;
+        if config.print_iteration != 0 {
+            println!("Resumed from {} at iteration {}, {} info sets.", path, iteration, agent.n_info_sets());
+        }
+        Ok(ParallelTrainer {
+            agent: ShardedAgent::from_agent(agent, n_threads * Self::SHARDS_PER_THREAD),
+            config,
+            n_threads,
+            iteration,
+        })
+    }
+
+    pub fn save_checkpoint(&self, path: &str) -> Result<()> {
+        write_checkpoint(path, self.iteration, |bytes| self.agent.write_tables(bytes))
+    }
+
+    pub fn train_with_checkpoints<F: Fn() -> S + Sync>(&mut self, new_root: F, path: &str) -> Result<()> {
+        // Like `train`, saving a checkpoint every `checkpoint_iteration`
+        // iterations and once more at the end.
+        let progress = Progress::start(self.iteration);
+        while self.iteration < self.config.n_iterations {
+            self.run_batch(&new_root, &progress);
+            if self.iteration.is_multiple_of(self.config.checkpoint_iteration) {
+                self.save_checkpoint(path)?;
+            }
+        }
+        self.save_checkpoint(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::bytes::temp_path;
+    use crate::cfr::best_response::exploitability;
+    use crate::cfr::trainer::Trainer;
+    use crate::poker::toy_games::{ToyGame, ToyGameState};
+
+    #[test]
+    fn solves_kuhn() {
+        let game = ToyGame::kuhn();
+        let root = game.root();
+        let config = TrainerConfig {
+            n_iterations: 20_000,
+            print_iteration: 0,
+            ..Default::default()
+        };
+        let mut trainer = ParallelTrainer::new(config, 4);
+        trainer.train(|| root.clone());
+        let result = exploitability(&root, |state: &ToyGameState| {
+            trainer.agent.average_strategy(&state.info_set(), state.legal_actions().len())
+        });
+        assert!(result.exploitability() < 0.02, "exploitability {}", result.exploitability());
+        assert!((result.policy_values[0] - game.game_value).abs() < 0.01, "value {}", result.policy_values[0]);
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        // Checkpoints hold one agent's tables whatever the sharding, so a
+        // single threaded trainer can resume from them too.
+        let root = ToyGame::kuhn().root();
+        let config = TrainerConfig {
+            n_iterations: 1000,
+            print_iteration: 0,
+            ..Default::default()
+        };
+        let mut trainer = ParallelTrainer::new(config, 2);
+        trainer.train(|| root.clone());
+        let path = temp_path("parallel-checkpoint");
+        trainer.save_checkpoint(&path).unwrap();
+        let resumed = ParallelTrainer::<ToyGameState>::resume(TrainerConfig::default(), 3, &path).unwrap();
+        let single = Trainer::<ToyGameState>::resume(TrainerConfig::default(), &path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(resumed.iteration(), 1000);
+        let (agent, resumed) = (trainer.into_agent(), resumed.into_agent());
+        assert_eq!(resumed.regret, agent.regret);
+        assert_eq!(resumed.strategy, agent.strategy);
+        assert_eq!(single.agent.strategy, agent.strategy);
+    }
+}
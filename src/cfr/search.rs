@@ -0,0 +1,462 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::agent::Agent;
+use super::game_state::GameState;
+use super::mccfr::{cfr, sample_action};
+use crate::poker::actions::Action;
+use crate::poker::info_set::InfoSetKey;
+use crate::poker::state::{BettingStage, PokerGameState};
+use crate::poker::strategy::BlueprintStrategy;
+
+/// Depth-limited search, as in Brown et al., "Superhuman AI for multiplayer
+/// poker" (2019). The subgame runs from the first decision of the current
+/// betting round to its end. There each player still in the hand picks one of
+/// `Continuation::ALL` for the rest of the hand, and the leaf is worth what
+/// a rollout with those strategies wins, so the search has to hold up against
+/// opponents who change their play after the leaf.
+///
+/// The other players' private cards are unknown, so each iteration deals them
+/// from the cards the searching player can not see, each hand weighted by the
+/// blueprint's probability of the actions its player took before this round.
+/// The actions already taken this round are replayed as played to reach the
+/// live state. The searching player's own are fixed, while the other players'
+/// are solved along with the rest of the round, so the strategy at the live
+/// state is consistent with how the round got there.
+pub struct SearchConfig {
+    pub max_iterations: usize,
+    pub max_time: Duration,
+    // How much more likely the biased continuations make their actions.
+    pub bias_factor: f64,
+    // Print the iterations and time each search took.
+    pub print_stats: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            max_iterations: 1000,
+            max_time: Duration::from_secs(5),
+            bias_factor: 5.0,
+            print_stats: false,
+        }
+    }
+}
+
+/// The blueprint, and the blueprint biased towards folding, calling or
+/// raising.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Continuation {
+    Blueprint,
+    Fold,
+    Call,
+    Raise,
+}
+
+impl Continuation {
+    pub const ALL: [Continuation; 4] =
+        [Continuation::Blueprint, Continuation::Fold, Continuation::Call, Continuation::Raise];
+
+    fn is_biased_to(&self, action: &Action) -> bool {
+        match action {
+            Action::Fold => *self == Continuation::Fold,
+            Action::Check | Action::Call => *self == Continuation::Call,
+            Action::Bet(_) | Action::RaiseTo(_) | Action::AllIn => *self == Continuation::Raise,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SubgameAction {
+    Poker(Action),
+    Continue(Continuation),
+}
+
+pub struct Search {
+    blueprint: Rc<BlueprintStrategy>,
+    config: SearchConfig,
+}
+
+impl Search {
+    pub fn new(blueprint: Rc<BlueprintStrategy>, config: SearchConfig) -> Self {
+        Search { blueprint, config }
+    }
+
+    pub fn search(&self, state: &PokerGameState) -> Action {
+        // Solves the subgame with MCCFR within the budget, then samples the
+        // searching player's action from the average strategy. Replaying the
+        // round reaches the live state with the same information set.
+        let root = self.subgame(state);
+        let mut agent = Agent::new();
+        let start = Instant::now();
+        let mut iteration = 0;
+        while iteration < self.config.max_iterations && start.elapsed() < self.config.max_time {
+            for i in 0..root.n_players() {
+                cfr(&mut agent, &root, i, None);
+            }
+            iteration += 1;
+        }
+        if self.config.print_stats {
+            println!("Searched {} iterations in {:.2}s.", iteration, start.elapsed().as_secs_f64());
+        }
+
+        let actions = state.legal_actions();
+        let sigma = agent.average_strategy(&state.info_set(), actions.len());
+        actions[sample_action(&sigma)]
+    }
+
+    fn subgame(&self, state: &PokerGameState) -> SubgameState {
+        // The subgame of the live state's betting round, rooted at its first
+        // decision, before the other players' hands are dealt.
+        let path = state.path_from_root();
+        let root_i = path
+            .iter()
+            .position(|s| s.betting_stage() == state.betting_stage() && s.is_decision_node())
+            .unwrap();
+        let searcher_i = state.player_i();
+        let subgame = Subgame {
+            searcher_i,
+            stage: state.betting_stage(),
+            ranges: self.ranges(&path[..=root_i], searcher_i),
+            replay: path[root_i + 1..]
+                .iter()
+                .map(|s| (s.action().unwrap(), s.abstract_action().unwrap()))
+                .collect(),
+            blueprint: Rc::clone(&self.blueprint),
+            bias_factor: self.config.bias_factor,
+        };
+        SubgameState {
+            state: path[root_i].clone(),
+            subgame: Rc::new(subgame),
+            is_dealt: false,
+            continuations: vec![None; state.n_players()],
+            n_replayed: Some(0),
+        }
+    }
+
+    fn ranges(&self, path: &[&PokerGameState], searcher_i: usize) -> Vec<Vec<([u8; 2], f64)>> {
+        // Every hand each player could hold at the end of `path`, weighted by
+        // the blueprint's probability of the actions they took along it. The
+        // searching player only holds their own hand.
+        let state = path.last().unwrap();
+        let known_cards = state.table().private_cards[searcher_i];
+        let mut unseen = state.deck();
+        for &card in known_cards.iter().chain(state.table().board()) {
+            unseen &= !(1 << card);
+        }
+        let cards: Vec<u8> = (0..64).filter(|&card| unseen & 1 << card != 0).collect();
+        let hands: Vec<[u8; 2]> = cards
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &first)| cards[i + 1..].iter().map(move |&second| [first, second]))
+            .collect();
+        (0..state.n_players())
+            .map(|player_i| {
+                if player_i == searcher_i {
+                    vec![(known_cards, 1.0)]
+                } else {
+                    hands.iter().map(|hand| (*hand, self.reach(path, player_i, hand))).collect()
+                }
+            })
+            .collect()
+    }
+
+    fn reach(&self, path: &[&PokerGameState], player_i: usize, hand: &[u8; 2]) -> f64 {
+        // The blueprint's probability of player `player_i` acting as they did
+        // along `path` when holding `hand`.
+        path.windows(2)
+            .filter(|states| states[0].is_decision_node() && states[0].player_i() == player_i)
+            .map(|states| {
+                let actions = states[0].legal_actions();
+                let action = states[1].abstract_action().unwrap();
+                let action_i = actions.iter().position(|a| *a == action).unwrap();
+                self.blueprint.action_probabilities(&states[0].info_set_with_cards(hand), actions.len())[action_i]
+            })
+            .product()
+    }
+}
+
+/// What every node of a subgame shares: each player's weighted hands, and
+/// the actions taken this round up to the live state, as played and as
+/// translated onto the action abstraction.
+struct Subgame {
+    searcher_i: usize,
+    stage: BettingStage,
+    ranges: Vec<Vec<([u8; 2], f64)>>,
+    replay: Vec<(Action, Action)>,
+    blueprint: Rc<BlueprintStrategy>,
+    bias_factor: f64,
+}
+
+/// A node of the subgame: the poker state, plus the continuation strategies
+/// chosen so far once the betting round is over.
+pub struct SubgameState {
+    state: PokerGameState,
+    subgame: Rc<Subgame>,
+    is_dealt: bool,
+    continuations: Vec<Option<Continuation>>,
+    // How many of this round's actions have been replayed, or None once the
+    // subgame has left the line that was played.
+    n_replayed: Option<usize>,
+}
+
+impl SubgameState {
+    const LEAF: u8 = InfoSetKey::STREET_END - 1;
+
+    fn is_leaf(&self) -> bool {
+        // The betting round the subgame started in is over.
+        !self.state.is_terminal()
+            && (self.state.betting_stage() != self.subgame.stage || self.state.is_chance_node())
+    }
+
+    fn next_to_continue(&self) -> Option<usize> {
+        (0..self.state.n_players())
+            .find(|&player_i| self.continuations[player_i].is_none() && self.state.table().is_active(player_i))
+    }
+
+    fn replay_action(&self) -> Option<(Action, Action)> {
+        // The action played next this round, if the subgame is still on it.
+        self.n_replayed.and_then(|n_replayed| self.subgame.replay.get(n_replayed).copied())
+    }
+
+    fn deal(&self) -> PokerGameState {
+        // Draws each player's hand by weight from those the hands drawn so far
+        // leave, uniformly if the blueprint rules all of them out.
+        let mut hands = Vec::new();
+        let mut dealt = 0u64;
+        for (player_i, range) in self.subgame.ranges.iter().enumerate() {
+            let open: Vec<&([u8; 2], f64)> =
+                range.iter().filter(|(hand, _)| hand.iter().all(|&card| dealt & 1 << card == 0)).collect();
+            if open.is_empty() {
+                panic!("No hand is left to deal player {}", player_i);
+            }
+            let mut weights: Vec<f64> = open.iter().map(|(_, weight)| *weight).collect();
+            if weights.iter().sum::<f64>() <= 0.0 {
+                weights = vec![1.0; open.len()];
+            }
+            let hand = open[sample_action(&weights)].0;
+            dealt |= 1 << hand[0] | 1 << hand[1];
+            hands.push(hand);
+        }
+        self.state.deal_private_cards(&hands)
+    }
+
+    fn rollout(&self) -> PokerGameState {
+        // Plays the hand out with every player following their continuation.
+        let mut state = self.state.clone();
+        while !state.is_terminal() {
+            if state.is_chance_node() {
+                state = state.sample_chance_outcome();
+                continue;
+            }
+            let observation = state.observation();
+            let continuation = self.continuations[state.player_i()].unwrap_or(Continuation::Blueprint);
+            let sigma: Vec<f64> = self.subgame.blueprint
+                .probabilities(&observation)
+                .into_iter()
+                .zip(observation.legal_actions.iter())
+                .map(|(p, action)| if continuation.is_biased_to(action) { p * self.subgame.bias_factor } else { p })
+                .collect();
+            state = state.apply_action(&observation.legal_actions[sample_action(&sigma)]);
+        }
+        state
+    }
+
+    fn with_state(&self, state: PokerGameState) -> Self {
+        SubgameState {
+            state,
+            subgame: Rc::clone(&self.subgame),
+            is_dealt: self.is_dealt,
+            continuations: self.continuations.clone(),
+            n_replayed: self.n_replayed,
+        }
+    }
+}
+
+impl GameState for SubgameState {
+    type Action = SubgameAction;
+    type InfoSet = InfoSetKey;
+
+    fn n_players(&self) -> usize {
+        self.state.n_players()
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.state.is_terminal()
+    }
+
+    fn is_chance_node(&self) -> bool {
+        !self.is_dealt || (self.is_leaf() && self.next_to_continue().is_none())
+    }
+
+    fn player_i(&self) -> usize {
+        if self.is_leaf() {
+            self.next_to_continue().unwrap()
+        } else {
+            self.state.player_i()
+        }
+    }
+
+    fn legal_actions(&self) -> Vec<SubgameAction> {
+        if self.is_leaf() {
+            return Continuation::ALL.iter().map(|&c| SubgameAction::Continue(c)).collect();
+        }
+        match self.replay_action() {
+            // The searching player's own actions this round stay as played.
+            Some((_, abstract_action)) if self.state.player_i() == self.subgame.searcher_i => {
+                vec![SubgameAction::Poker(abstract_action)]
+            }
+            _ => self.state.legal_actions().into_iter().map(SubgameAction::Poker).collect(),
+        }
+    }
+
+    fn apply_action(&self, action: &SubgameAction) -> Self {
+        match action {
+            SubgameAction::Poker(action) => match self.replay_action() {
+                // Following the line that was played replays the action as
+                // taken, even if it was off the action abstraction.
+                Some((played, abstract_action)) if *action == abstract_action => {
+                    let mut subgame = self.with_state(self.state.apply_translated_action(&played, &abstract_action));
+                    subgame.n_replayed = self.n_replayed.map(|n_replayed| n_replayed + 1);
+                    subgame
+                }
+                _ => {
+                    let mut subgame = self.with_state(self.state.apply_action(action));
+                    subgame.n_replayed = None;
+                    subgame
+                }
+            },
+            SubgameAction::Continue(continuation) => {
+                let mut subgame = self.with_state(self.state.clone());
+                subgame.continuations[self.player_i()] = Some(*continuation);
+                subgame
+            }
+        }
+    }
+
+    fn sample_chance_outcome(&self) -> Self {
+        if !self.is_dealt {
+            let mut subgame = self.with_state(self.deal());
+            subgame.is_dealt = true;
+            subgame
+        } else {
+            self.with_state(self.rollout())
+        }
+    }
+
+    fn payout(&self, player_i: usize) -> f64 {
+        self.state.payout(player_i)
+    }
+
+    fn info_set(&self) -> InfoSetKey {
+        if self.is_leaf() {
+            // Choosing a continuation is a different decision from acting at
+            // the start of the next betting round.
+            self.state.info_set_of(self.player_i()).extended(&[SubgameState::LEAF])
+        } else {
+            self.state.info_set()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cfr::trainer::{Trainer, TrainerConfig};
+    use crate::poker::action_abstraction::ActionAbstraction;
+    use crate::poker::deck::Deck;
+
+    fn short_deck_hand() -> PokerGameState {
+        // Heads up with stacks of 10 big blinds, a twelve card deck and pot
+        // sized bets or all in on the first two streets.
+        let deck = Deck::new(Some(vec!["spades", "hearts"]), Some((9..=14).collect()));
+        let mut root = PokerGameState::new_hand(&[20, 20], &deck, 1, 2);
+        let abstraction = ActionAbstraction::parse("preflop 0 1 allin\nflop 0 1 allin").unwrap();
+        root.set_action_abstraction(Arc::new(abstraction));
+        root
+    }
+
+    fn play(state: PokerGameState, actions: &[Action]) -> PokerGameState {
+        // Deals whenever cards are due.
+        actions.iter().fold(state, |state, action| {
+            let state = if state.is_chance_node() { state.sample_chance_outcome() } else { state };
+            state.apply_action(action)
+        })
+    }
+
+    fn search(blueprint: BlueprintStrategy) -> Search {
+        Search::new(Rc::new(blueprint), SearchConfig { max_iterations: 200, ..Default::default() })
+    }
+
+    #[test]
+    fn ranges_weigh_hands_by_blueprint_reach() {
+        // The small blind raised pre-flop and the big blind is to act on the
+        // flop, so the small blind's hands are weighted by that raise.
+        let state = play(short_deck_hand(), &[Action::RaiseTo(6), Action::Call]).sample_chance_outcome();
+        let path = state.path_from_root();
+        let opening = path[1];
+        let actions = opening.legal_actions();
+        let raise_i = actions.iter().position(|a| *a == path[2].abstract_action().unwrap()).unwrap();
+
+        let uniform = search(BlueprintStrategy::new(HashMap::new())).subgame(&state);
+        let ranges = &uniform.subgame.ranges;
+        assert_eq!(ranges[1], vec![(state.table().private_cards[1], 1.0)]);
+        // Every pair of the 7 cards the big blind can not see, all equally likely.
+        assert_eq!(ranges[0].len(), 21);
+        let seen: Vec<u8> = state.table().private_cards[1].iter().chain(state.table().board()).copied().collect();
+        for (hand, weight) in &ranges[0] {
+            assert!(hand.iter().all(|card| !seen.contains(card)));
+            assert!((weight - 1.0 / actions.len() as f64).abs() < 1e-9);
+        }
+
+        // A blueprint that never raises with one of those hands rules it out.
+        let never_raised = ranges[0][0].0;
+        let mut sigma = vec![1.0 / (actions.len() - 1) as f64; actions.len()];
+        sigma[raise_i] = 0.0;
+        let blueprint = BlueprintStrategy::new(HashMap::from([(opening.info_set_with_cards(&never_raised), sigma)]));
+        let subgame = search(blueprint).subgame(&state);
+        let ranges = &subgame.subgame.ranges;
+        assert_eq!(ranges[0][0], (never_raised, 0.0));
+        assert!(ranges[0][1..].iter().all(|&(_, weight)| weight > 0.0));
+        for _ in 0..100 {
+            assert_ne!(subgame.sample_chance_outcome().state.table().private_cards[0], never_raised);
+        }
+    }
+
+    #[test]
+    fn search_replays_the_round_and_returns_a_legal_action() {
+        let root = short_deck_hand();
+        let mut trainer = Trainer::new(TrainerConfig { n_iterations: 2_000, print_iteration: 0, ..Default::default() });
+        trainer.train(|| root.clone());
+        let search = search(BlueprintStrategy::from_agent(&trainer.agent));
+
+        // The big blind checked the flop and faces a bet off the abstraction.
+        let state = play(root, &[Action::Call, Action::Check, Action::Check, Action::Bet(6)]);
+        assert_eq!(state.player_i(), 1);
+
+        let subgame = search.subgame(&state);
+        assert_eq!(subgame.state.betting_stage(), BettingStage::Flop);
+        assert_eq!(subgame.state.history()[0], state.history()[0]);
+        assert!(subgame.state.history()[1].is_empty());
+        // Dealt, the subgame starts with the big blind's check, which stays as
+        // played, and replaying the bet reaches the live state.
+        let dealt = subgame.sample_chance_outcome();
+        assert_eq!(dealt.state.table().private_cards[1], state.table().private_cards[1]);
+        assert_eq!(dealt.legal_actions(), vec![SubgameAction::Poker(Action::Check)]);
+        let checked = dealt.apply_action(&SubgameAction::Poker(Action::Check));
+        assert_eq!(checked.player_i(), 0);
+        assert!(checked.legal_actions().len() > 1);
+        let live = checked.apply_action(&SubgameAction::Poker(state.abstract_action().unwrap()));
+        assert_eq!(live.n_replayed, Some(2));
+        assert_eq!(live.state.history(), state.history());
+        assert_eq!(live.info_set(), state.info_set());
+
+        for _ in 0..5 {
+            let action = search.search(&state);
+            assert!(state.legal_actions().contains(&action), "{} is not legal", action);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use rand::{thread_rng, Rng};
+
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::time::Instant;
+
+use super::agent::Agent;
+use super::game_state::GameState;
+use super::mccfr;
+use crate::bytes::ByteReader;
+
+/// Iteration counts and thresholds for a training run. The linear CFR and
+/// pruning defaults follow the Pluribus paper, with minutes of training
+/// replaced by iterations.
+pub struct TrainerConfig {
+    // The iteration to train up to, counting those of a resumed checkpoint.
+    pub n_iterations: usize,
+    // Print progress every this many iterations, 0 to stay quiet.
+    pub print_iteration: usize,
+    // Linear CFR: until this iteration, every `discount_interval` iterations
+    // the regret and strategy tables are scaled by (t / d) / (t / d + 1).
+    pub lcfr_threshold: usize,
+    pub discount_interval: usize,
+    // After this iteration, traversals skip actions with regret below
+    // `prune_regret`, except for a `prune_explore_probability` share of
+    // traversals which still explore everything.
+    pub prune_threshold: usize,
+    pub prune_regret: f64,
+    pub prune_explore_probability: f64,
+    // Save a checkpoint every this many iterations when training with
+    // checkpoints.
+    pub checkpoint_iteration: usize,
+}
+
+impl TrainerConfig {
+    pub fn prune_threshold(&self, iteration: usize) -> Option<f64> {
+        // The regret below which the traversals of `iteration` skip actions.
+        let pruning = iteration > self.prune_threshold
+            && thread_rng().gen::<f64>() >= self.prune_explore_probability;
+        if pruning {
+            Some(self.prune_regret)
+        } else {
+            None
+        }
+    }
+
+    pub fn discount(&self, iteration: usize) -> Option<f64> {
+        // The linear CFR factor to scale the tables by after `iteration`.
+        if iteration < self.lcfr_threshold && iteration.is_multiple_of(self.discount_interval) {
+            let n_discounts = (iteration / self.discount_interval) as f64;
+            Some(n_discounts / (n_discounts + 1.0))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for TrainerConfig {
+    fn default() -> Self {
+        TrainerConfig {
+            n_iterations: 1000,
+            print_iteration: 100,
+            lcfr_threshold: 400,
+            discount_interval: 10,
+            prune_threshold: 200,
+            prune_regret: -300_000_000.0,
+            prune_explore_probability: 0.05,
+            checkpoint_iteration: 1000,
+        }
+    }
+}
+
+/// Drives MCCFR iterations. Each iteration draws a fresh root from the
+/// supplied closure and runs one traversal per player.
+///
+/// Checkpoints hold the iteration and both of the agent's tables at full
+/// precision, so an interrupted run resumes where it stopped:
+///                 +-------+---------+-----------+--------------+----------------+
+///                 | magic | version | iteration | regret table | strategy table |
+///                 +-------+---------+-----------+--------------+----------------+
+///    1) magic = the bytes "PLCK", version = u32, iteration = u64
+///    2) tables = as written by `Agent::write_tables`
+///
+/// `ParallelTrainer` reads and writes the same checkpoints.
+pub struct Trainer<S: GameState> {
+    pub agent: Agent<S::InfoSet>,
+    config: TrainerConfig,
+    iteration: usize,
+}
+
+impl<S: GameState> Trainer<S> {
+    pub fn new(config: TrainerConfig) -> Self {
+        Trainer {
+            agent: Agent::new(),
+            config,
+            iteration: 0,
+        }
+    }
+
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    pub fn train<F: FnMut() -> S>(&mut self, mut new_root: F) {
+        let progress = Progress::start(self.iteration);
+        while self.iteration < self.config.n_iterations {
+            self.run_iteration(&mut new_root, &progress);
+        }
+    }
+
+    fn run_iteration<F: FnMut() -> S>(&mut self, new_root: &mut F, progress: &Progress) {
+        self.iteration += 1;
+        let root = new_root();
+        for i in 0..root.n_players() {
+            let prune_threshold = self.config.prune_threshold(self.iteration);
+            mccfr::cfr(&mut self.agent, &root, i, prune_threshold);
+        }
+        if let Some(d) = self.config.discount(self.iteration) {
+            self.agent.discount(d);
+        }
+        if self.iteration.is_multiple_of(self.config.print_iteration) {
+            progress.print(self.iteration, self.agent.n_info_sets());
+        }
+    }
+}
+
+impl<S: GameState> Trainer<S>
+where
+    S::InfoSet: AsRef<[u8]> + for<'a> From<&'a [u8]>,
+{
+    pub fn resume(config: TrainerConfig, path: &str) -> Result<Self> {
+        let (iteration, agent) = read_checkpoint(path, Agent::read_tables)?;
+        if config.print_iteration != 0 {
+            println!("Resumed from {} at iteration {}, {} info sets.", path, iteration, agent.n_info_sets());
+        }
+        Ok(Trainer { agent, config, iteration })
+    }
+
+    pub fn save_checkpoint(&self, path: &str) -> Result<()> {
+        write_checkpoint(path, self.iteration, |bytes| self.agent.write_tables(bytes))
+    }
+
+    pub fn train_with_checkpoints<F: FnMut() -> S>(&mut self, mut new_root: F, path: &str) -> Result<()> {
+        // Like `train`, saving a checkpoint every `checkpoint_iteration`
+        // iterations and once more at the end.
+        let progress = Progress::start(self.iteration);
+        while self.iteration < self.config.n_iterations {
+            self.run_iteration(&mut new_root, &progress);
+            if self.iteration.is_multiple_of(self.config.checkpoint_iteration) {
+                self.save_checkpoint(path)?;
+            }
+        }
+        self.save_checkpoint(path)
+    }
+}
+
+pub const CHECKPOINT_MAGIC: &[u8; 4] = b"PLCK";
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+pub fn write_checkpoint<W>(path: &str, iteration: usize, write_tables: W) -> Result<()>
+where
+    W: FnOnce(&mut Vec<u8>) -> Result<()>,
+{
+    // Written beside the old checkpoint and then renamed over it, so an
+    // interruption while saving leaves the last checkpoint intact.
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(CHECKPOINT_MAGIC);
+    bytes.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(iteration as u64).to_le_bytes());
+    write_tables(&mut bytes)?;
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)
+}
+
+pub fn read_checkpoint<T, R>(path: &str, read_tables: R) -> Result<(usize, T)>
+where
+    R: FnOnce(&mut ByteReader) -> Result<T>,
+{
+    let bytes = fs::read(path)?;
+    let mut reader = ByteReader::new(&bytes, "Checkpoint");
+    reader.expect_header(CHECKPOINT_MAGIC, CHECKPOINT_VERSION)?;
+    let iteration = reader.u64()? as usize;
+    let tables = read_tables(&mut reader)?;
+    if !reader.is_at_end() {
+        return Err(Error::new(ErrorKind::InvalidData, "Unexpected bytes after the checkpoint tables"));
+    }
+    Ok((iteration, tables))
+}
+
+/// Progress reports for a training run, with the throughput since it started.
+pub struct Progress {
+    start: Instant,
+    start_iteration: usize,
+}
+
+impl Progress {
+    pub fn start(iteration: usize) -> Self {
+        Progress { start: Instant::now(), start_iteration: iteration }
+    }
+
+    pub fn print(&self, iteration: usize, n_info_sets: usize) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        println!(
+            "Iteration {}, {} info sets, {:.1}s elapsed, {:.0} iterations/s.",
+            iteration,
+            n_info_sets,
+            elapsed,
+            (iteration - self.start_iteration) as f64 / elapsed
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::bytes::temp_path;
+    use crate::poker::toy_games::{ToyGame, ToyGameState};
+
+    fn trained_kuhn() -> Trainer<ToyGameState> {
+        let root = ToyGame::kuhn().root();
+        let mut trainer = Trainer::new(TrainerConfig {
+            n_iterations: 1000,
+            print_iteration: 0,
+            ..Default::default()
+        });
+        trainer.train(|| root.clone());
+        trainer
+    }
+
+    fn resume_error(bytes: &[u8], name: &str) -> Option<ErrorKind> {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        let result = Trainer::<ToyGameState>::resume(TrainerConfig::default(), &path);
+        fs::remove_file(&path).unwrap();
+        result.err().map(|error| error.kind())
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let trainer = trained_kuhn();
+        let path = temp_path("checkpoint-round-trip");
+        trainer.save_checkpoint(&path).unwrap();
+        let resumed = Trainer::<ToyGameState>::resume(TrainerConfig::default(), &path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.iteration(), 1000);
+        assert_eq!(resumed.agent.regret, trainer.agent.regret);
+        assert_eq!(resumed.agent.strategy, trainer.agent.strategy);
+    }
+
+    #[test]
+    fn checkpoint_rejects_corrupt_files() {
+        let path = temp_path("checkpoint-corrupt");
+        trained_kuhn().save_checkpoint(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert_eq!(resume_error(&wrong_magic, "checkpoint-magic"), Some(ErrorKind::InvalidData));
+        let mut wrong_version = bytes.clone();
+        wrong_version[4..8].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+        assert_eq!(resume_error(&wrong_version, "checkpoint-version"), Some(ErrorKind::InvalidData));
+        assert_eq!(resume_error(&bytes[..bytes.len() - 1], "checkpoint-truncated"), Some(ErrorKind::InvalidData));
+        let mut extra_bytes = bytes.clone();
+        extra_bytes.push(0);
+        assert_eq!(resume_error(&extra_bytes, "checkpoint-extra"), Some(ErrorKind::InvalidData));
+        // A table count far beyond the file, which must not be preallocated.
+        let mut huge_count = bytes;
+        huge_count[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(resume_error(&huge_count, "checkpoint-count"), Some(ErrorKind::InvalidData));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod card_abstraction;
+pub mod card_combos;
+pub mod hand_indexer;
+pub mod histograms;
+pub mod kmeans;
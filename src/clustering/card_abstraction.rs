@@ -0,0 +1,328 @@
+/// Information abstraction: groups the suit-isomorphism classes of each street
+/// into a fixed number of buckets of strategically similar hands.
+///
+/// Pre-flop, flop and turn hands are described by their histogram of river
+/// equity and the river by its equity alone, then each street is clustered
+/// with k-means under the earth mover's distance. A street asked for at least
+/// as many buckets as it has classes is kept lossless, one bucket per class.
+///
+/// k-means is fitted on a random sample of each street's classes, and every
+/// class is then given the bucket of its nearest centroid in one pass, so
+/// features are never held for a whole street. The tables still store a u32
+/// per class: a full deck has about 2.4 billion river classes, which take
+/// around 10 GB and a long time to compute, so full-deck abstractions need a
+/// large machine, and short decks are the practical choice for experiments.
+///
+/// Computed tables can be saved to and loaded from a binary file:
+///                 +-------+---------+-------------+-------------+
+///                 | magic | version | deck config | street x 4  | tables
+///                 +-------+---------+-------------+-------------+
+///    1) magic = the bytes "PLBK", version = u32
+///    2) deck config = n_suits: u8, a suit initial per suit, n_ranks: u8,
+///       the ranks as u8
+///    3) per street = n_buckets: u32, n_classes: u64, bytes_per_bucket: u8
+///    4) tables = every street's buckets in hand index order, each stored in
+///       the fewest bytes (1, 2 or 4) that hold its bucket count
+///
+/// All integers are little endian.
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+use rand::seq::index;
+use rand::thread_rng;
+
+use super::card_combos::CardCombos;
+use super::hand_indexer::HandIndexer;
+use super::histograms::{equity_histogram, river_equity};
+use super::kmeans::KMeans;
+use crate::bytes::ByteReader;
+use crate::poker::card::Card;
+use crate::poker::deck::Deck;
+use crate::poker::evaluation::evaluator::Evaluator;
+use crate::poker::state::BettingStage;
+
+pub struct CardAbstractionConfig {
+    // Buckets for the pre-flop, flop, turn and river.
+    pub n_buckets: [usize; 4],
+    pub n_bins: usize,
+    // Runouts and opponent hands sampled per equity estimate, 0 to enumerate.
+    pub n_runouts: usize,
+    pub n_opponent_samples: usize,
+    pub max_iterations: usize,
+    // Classes per street k-means is fitted on, 0 to fit on every class.
+    pub n_training_samples: usize,
+}
+
+impl Default for CardAbstractionConfig {
+    fn default() -> Self {
+        CardAbstractionConfig {
+            n_buckets: [169, 200, 200, 200],
+            n_bins: 50,
+            n_runouts: 100,
+            n_opponent_samples: 100,
+            max_iterations: 100,
+            n_training_samples: 100_000,
+        }
+    }
+}
+
+pub struct CardAbstraction {
+    pub n_buckets: [usize; 4],
+    // For each street, the bucket of every class, indexed by hand index.
+    pub buckets: Vec<Vec<u32>>,
+    include_suits: Vec<&'static str>,
+    include_ranks: Vec<i32>,
+    indexer: HandIndexer,
+}
+
+impl CardAbstraction {
+    pub const MAGIC: &'static [u8; 4] = b"PLBK";
+    pub const VERSION: u32 = 1;
+
+    pub fn compute(deck: &Deck, config: &CardAbstractionConfig) -> Self {
+        let combos = CardCombos::new(deck);
+        let evaluator = Evaluator::new();
+        let mut n_buckets = [0; 4];
+        let mut buckets = Vec::new();
+        for (street_i, street) in HandIndexer::STREETS.iter().enumerate() {
+            let n_classes = combos.indexer.size(*street) as usize;
+            let features = |(hand, board): (Vec<Card>, Vec<Card>)| {
+                if *street == BettingStage::River {
+                    vec![river_equity(&evaluator, combos.cards(), &hand, &board, config.n_opponent_samples)]
+                } else {
+                    equity_histogram(
+                        &evaluator,
+                        combos.cards(),
+                        &hand,
+                        &board,
+                        config.n_bins,
+                        config.n_runouts,
+                        config.n_opponent_samples,
+                    )
+                }
+            };
+            let street_buckets = if config.n_buckets[street_i] >= n_classes {
+                (0..n_classes as u32).collect()
+            } else if config.n_training_samples == 0 || config.n_training_samples >= n_classes {
+                let points: Vec<Vec<f64>> = combos.canonical_combos(*street).map(features).collect();
+                KMeans::new(config.n_buckets[street_i], config.max_iterations).fit(&points).assignments
+            } else {
+                let sample = index::sample(&mut thread_rng(), n_classes, config.n_training_samples);
+                let points: Vec<Vec<f64>> = sample
+                    .iter()
+                    .map(|class_i| features(combos.indexer.unindex(*street, class_i as u64)))
+                    .collect();
+                let clustering = KMeans::new(config.n_buckets[street_i], config.max_iterations).fit(&points);
+                combos
+                    .canonical_combos(*street)
+                    .map(|combo| clustering.nearest(&features(combo)))
+                    .collect()
+            };
+            n_buckets[street_i] = config.n_buckets[street_i].min(n_classes);
+            buckets.push(street_buckets);
+        }
+        CardAbstraction {
+            n_buckets,
+            buckets,
+            include_suits: deck.include_suits().to_vec(),
+            include_ranks: deck.include_ranks().to_vec(),
+            indexer: combos.indexer,
+        }
+    }
+
+    pub fn bucket(&self, street: BettingStage, hand: &[Card], board: &[Card]) -> u32 {
+        if HandIndexer::street_from_board(board.len()) != street {
+            panic!("A {:?} board can not have {} cards", street, board.len());
+        }
+        self.buckets(street)[self.indexer.index(hand, board) as usize]
+    }
+
+    pub fn buckets(&self, street: BettingStage) -> &[u32] {
+        let street_i = HandIndexer::STREETS.iter().position(|&s| s == street).unwrap();
+        &self.buckets[street_i]
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        bytes.push(self.include_suits.len() as u8);
+        for suit in self.include_suits.iter() {
+            bytes.push(suit.as_bytes()[0]);
+        }
+        bytes.push(self.include_ranks.len() as u8);
+        for rank in self.include_ranks.iter() {
+            bytes.push(*rank as u8);
+        }
+        for (street_buckets, n_buckets) in self.buckets.iter().zip(self.n_buckets) {
+            bytes.extend_from_slice(&(n_buckets as u32).to_le_bytes());
+            bytes.extend_from_slice(&(street_buckets.len() as u64).to_le_bytes());
+            bytes.push(Self::bytes_per_bucket(n_buckets) as u8);
+        }
+        for (street_buckets, n_buckets) in self.buckets.iter().zip(self.n_buckets) {
+            let width = Self::bytes_per_bucket(n_buckets);
+            for bucket in street_buckets {
+                bytes.extend_from_slice(&bucket.to_le_bytes()[..width]);
+            }
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: &str) -> Result<CardAbstraction> {
+        // Reads the whole file into memory and rebuilds the hand indexer for
+        // the deck the tables were computed on. Anything the tables are later
+        // indexed with is checked here, so a corrupt file is an error rather
+        // than a panic.
+        let invalid = |message: String| Error::new(ErrorKind::InvalidData, message);
+        let bytes = fs::read(path)?;
+        let mut reader = ByteReader::new(&bytes, "Card abstraction");
+        reader.expect_header(Self::MAGIC, Self::VERSION)?;
+
+        let n_suits = reader.u8()? as usize;
+        let mut include_suits = Vec::new();
+        for initial in reader.take(n_suits)? {
+            let suit = Deck::DEFAULT_INCLUDE_SUITS
+                .iter()
+                .find(|suit| suit.as_bytes()[0] == *initial)
+                .ok_or_else(|| invalid(format!("Unknown suit '{}' in deck config", *initial as char)))?;
+            if include_suits.contains(suit) {
+                return Err(invalid(format!("Suit {} repeated in deck config", suit)));
+            }
+            include_suits.push(*suit);
+        }
+        let n_ranks = reader.u8()? as usize;
+        let mut include_ranks = Vec::new();
+        for &rank in reader.take(n_ranks)? {
+            let rank = rank as i32;
+            if !Deck::DEFAULT_INCLUDE_RANKS.contains(&rank) || include_ranks.contains(&rank) {
+                return Err(invalid(format!("Bad or repeated rank {} in deck config", rank)));
+            }
+            include_ranks.push(rank);
+        }
+        let deck = Deck::new(Some(include_suits.clone()), Some(include_ranks.clone()));
+        let indexer = HandIndexer::new(&deck);
+
+        let mut n_buckets = [0; 4];
+        let mut street_shapes = Vec::new();
+        for (street_i, street) in HandIndexer::STREETS.iter().enumerate() {
+            n_buckets[street_i] = reader.u32()? as usize;
+            let n_classes = reader.u64()?;
+            let width = reader.u8()? as usize;
+            if n_classes != indexer.size(*street) || width != Self::bytes_per_bucket(n_buckets[street_i]) {
+                return Err(invalid(format!("Corrupt {:?} table header", street)));
+            }
+            street_shapes.push((n_classes as usize, width));
+        }
+        let mut buckets = Vec::new();
+        for (street_i, (n_classes, width)) in street_shapes.into_iter().enumerate() {
+            let data = reader.take(n_classes * width)?;
+            let street_buckets: Vec<u32> = data
+                .chunks(width)
+                .map(|chunk| {
+                    let mut le_bytes = [0u8; 4];
+                    le_bytes[..width].copy_from_slice(chunk);
+                    u32::from_le_bytes(le_bytes)
+                })
+                .collect();
+            if street_buckets.iter().any(|&bucket| bucket as usize >= n_buckets[street_i]) {
+                let street = HandIndexer::STREETS[street_i];
+                return Err(invalid(format!("{:?} bucket out of range of {}", street, n_buckets[street_i])));
+            }
+            buckets.push(street_buckets);
+        }
+        if !reader.is_at_end() {
+            return Err(invalid("Unexpected bytes after the bucket tables".to_string()));
+        }
+
+        Ok(CardAbstraction {
+            n_buckets,
+            buckets,
+            include_suits,
+            include_ranks,
+            indexer,
+        })
+    }
+
+    fn bytes_per_bucket(n_buckets: usize) -> usize {
+        if n_buckets <= 1 << 8 {
+            1
+        } else if n_buckets <= 1 << 16 {
+            2
+        } else {
+            4
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes::temp_path;
+
+    fn small_abstraction() -> CardAbstraction {
+        let deck = Deck::new(Some(vec!["spades", "hearts", "diamonds"]), Some(vec![12, 13, 14]));
+        let config = CardAbstractionConfig {
+            n_buckets: [3, 3, 3, 3],
+            n_bins: 5,
+            n_runouts: 2,
+            n_opponent_samples: 2,
+            max_iterations: 10,
+            n_training_samples: 0,
+        };
+        CardAbstraction::compute(&deck, &config)
+    }
+
+    fn load_error(bytes: &[u8], name: &str) -> Option<ErrorKind> {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        let result = CardAbstraction::load(&path);
+        fs::remove_file(&path).unwrap();
+        result.err().map(|error| error.kind())
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let abstraction = small_abstraction();
+        let path = temp_path("abstraction-round-trip");
+        abstraction.save(&path).unwrap();
+        let loaded = CardAbstraction::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.n_buckets, abstraction.n_buckets);
+        assert_eq!(loaded.buckets, abstraction.buckets);
+        let hand = [Card::new("A", "spades"), Card::new("K", "hearts")];
+        let board = [Card::new("Q", "spades"), Card::new("Q", "hearts"), Card::new("K", "diamonds")];
+        assert_eq!(
+            loaded.bucket(BettingStage::Flop, &hand, &board),
+            abstraction.bucket(BettingStage::Flop, &hand, &board)
+        );
+    }
+
+    #[test]
+    fn load_rejects_corrupt_files() {
+        let path = temp_path("abstraction-corrupt");
+        small_abstraction().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // The three suit initials follow the header and suit count, then the
+        // rank count and the ranks.
+        let first_rank = 13;
+        assert_eq!(bytes[first_rank], 12);
+
+        let mut bad_suit = bytes.clone();
+        bad_suit[9] = b'x';
+        assert_eq!(load_error(&bad_suit, "abstraction-suit"), Some(ErrorKind::InvalidData));
+        for rank in [0, 1, 15, 13] {
+            let mut bad_rank = bytes.clone();
+            bad_rank[first_rank] = rank;
+            assert_eq!(load_error(&bad_rank, "abstraction-rank"), Some(ErrorKind::InvalidData), "rank {}", rank);
+        }
+        let mut bad_bucket = bytes.clone();
+        *bad_bucket.last_mut().unwrap() = 3;
+        assert_eq!(load_error(&bad_bucket, "abstraction-bucket"), Some(ErrorKind::InvalidData));
+        assert_eq!(load_error(&bytes[..bytes.len() - 1], "abstraction-truncated"), Some(ErrorKind::InvalidData));
+        let mut extra_bytes = bytes;
+        extra_bytes.push(0);
+        assert_eq!(load_error(&extra_bytes, "abstraction-extra"), Some(ErrorKind::InvalidData));
+    }
+}
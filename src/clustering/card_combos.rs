@@ -0,0 +1,94 @@
+/// Enumerates the card combinations that information abstraction is computed
+/// over: the canonical starting hands, and for each of them every flop, turn
+/// and river board that can be dealt alongside it.
+///
+/// Starting hands are reduced to one representative per class (pairs, suited
+/// and offsuit hands), since the suits of the private cards alone carry no
+/// strategic information. Boards are not reduced, so the street combinations
+/// are produced lazily - on a full deck there are hundreds of millions of
+/// river combinations. `canonical_combos` goes further and yields a single
+/// representative per suit-isomorphism class of (hand, board).
+use itertools::Itertools;
+
+use super::hand_indexer::HandIndexer;
+use crate::poker::card::Card;
+use crate::poker::deck::Deck;
+use crate::poker::state::BettingStage;
+
+pub struct CardCombos {
+    cards: Vec<Card>,
+    pub starting_hands: Vec<Vec<Card>>,
+    pub indexer: HandIndexer,
+}
+
+impl CardCombos {
+    pub fn new(deck: &Deck) -> Self {
+        let cards = deck.all_cards();
+        let starting_hands = CardCombos::canonical_starting_hands(deck);
+        CardCombos {
+            cards,
+            starting_hands,
+            indexer: HandIndexer::new(deck),
+        }
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    pub fn card_combos(&self, n_cards: usize) -> impl Iterator<Item = Vec<Card>> + '_ {
+        self.cards.iter().cloned().combinations(n_cards)
+    }
+
+    pub fn flop(&self) -> impl Iterator<Item = (Vec<Card>, Vec<Card>)> + '_ {
+        self.info_combos(3)
+    }
+
+    pub fn turn(&self) -> impl Iterator<Item = (Vec<Card>, Vec<Card>)> + '_ {
+        self.info_combos(4)
+    }
+
+    pub fn river(&self) -> impl Iterator<Item = (Vec<Card>, Vec<Card>)> + '_ {
+        self.info_combos(5)
+    }
+
+    pub fn canonical_combos(&self, street: BettingStage) -> impl Iterator<Item = (Vec<Card>, Vec<Card>)> + '_ {
+        // In index order, so the n-th item is the class with index n.
+        (0..self.indexer.size(street)).map(move |index| self.indexer.unindex(street, index))
+    }
+
+    fn info_combos(&self, n_public_cards: usize) -> impl Iterator<Item = (Vec<Card>, Vec<Card>)> + '_ {
+        // Every (hand, board) pair where the board does not reuse a private card.
+        self.starting_hands.iter().flat_map(move |hand| {
+            self.cards
+                .iter()
+                .filter(move |card| !hand.contains(card))
+                .cloned()
+                .combinations(n_public_cards)
+                .map(move |board| (hand.clone(), board))
+        })
+    }
+
+    fn canonical_starting_hands(deck: &Deck) -> Vec<Vec<Card>> {
+        // Highest ranks first; the first suits of the deck stand in for any
+        // suits, and the first two for any pair of different suits.
+        let suits = deck.include_suits();
+        let mut ranks = deck.include_ranks().to_vec();
+        ranks.sort_by(|a, b| b.cmp(a));
+
+        let mut starting_hands = Vec::new();
+        for (i, high_rank) in ranks.iter().enumerate() {
+            for low_rank in ranks[i..].iter() {
+                let high = high_rank.to_string();
+                let low = low_rank.to_string();
+                if high_rank != low_rank {
+                    starting_hands.push(vec![Card::new(&high, suits[0]), Card::new(&low, suits[0])]);
+                }
+                if suits.len() > 1 {
+                    starting_hands.push(vec![Card::new(&high, suits[0]), Card::new(&low, suits[1])]);
+                }
+            }
+        }
+        starting_hands
+    }
+}
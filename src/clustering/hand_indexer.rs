@@ -0,0 +1,475 @@
+/// Maps hands to indices of their suit-isomorphism class and back, following
+/// Waugh's "A Fast and Optimal Hand Isomorphism Algorithm" (2013).
+///
+/// Permuting suits never changes the strength of a hand, so (AsKs, Qs7h2d)
+/// and (AhKh, Qh7s2d) are the same hand strategically. For each street the
+/// indexer assigns every class a dense index in 0..size(street), where the
+/// private cards, flop, turn and river are separate rounds: moving a card
+/// between rounds gives a different hand.
+///
+/// How an index is built:
+/// 1) For every suit, the ranks held in each round form a rank set. The
+///    number of cards per round in a suit is its count vector.
+/// 2) Each suit's rank sets become one integer: each round's set is indexed
+///    among the ranks that suit has not used in earlier rounds, and the round
+///    indices are combined as a mixed-radix number.
+/// 3) Suits with equal count vectors are interchangeable, so their integers
+///    are combined as a multiset. The multiset of count vectors across all
+///    suits is the hand's configuration, and each configuration owns a
+///    contiguous range of indices.
+///
+/// On a full deck this gives 169 pre-flop, 1,286,792 flop, 55,190,538 turn
+/// and 2,428,287,420 river classes.
+use std::collections::HashMap;
+
+use crate::poker::card::Card;
+use crate::poker::deck::Deck;
+use crate::poker::state::BettingStage;
+
+pub struct HandIndexer {
+    ranks: Vec<i32>,
+    suits: Vec<&'static str>,
+    streets: Vec<StreetIndexer>,
+}
+
+impl HandIndexer {
+    pub const STREETS: [BettingStage; 4] = [
+        BettingStage::PreFlop,
+        BettingStage::Flop,
+        BettingStage::Turn,
+        BettingStage::River,
+    ];
+
+    pub fn new(deck: &Deck) -> Self {
+        let mut ranks = deck.include_ranks().to_vec();
+        ranks.sort();
+        let suits = deck.include_suits().to_vec();
+        let cards_per_round = [2, 3, 1, 1];
+        let streets = (1..=cards_per_round.len())
+            .map(|n_rounds| StreetIndexer::new(ranks.len(), suits.len(), &cards_per_round[..n_rounds]))
+            .collect();
+        HandIndexer {
+            ranks,
+            suits,
+            streets,
+        }
+    }
+
+    pub fn size(&self, street: BettingStage) -> u64 {
+        self.streets[Self::street_i(street)].size
+    }
+
+    pub fn index(&self, hand: &[Card], board: &[Card]) -> u64 {
+        // The street follows from the number of public cards.
+        let street = Self::street_from_board(board.len());
+        let indexer = &self.streets[Self::street_i(street)];
+        let mut rank_sets = vec![vec![0u16; indexer.n_rounds()]; self.suits.len()];
+        let rounds = [hand, &board[..board.len().min(3)], board.get(3..4).unwrap_or(&[]), board.get(4..5).unwrap_or(&[])];
+        for (round_i, cards) in rounds.iter().enumerate().take(indexer.n_rounds()) {
+            for card in cards.iter() {
+                let (rank_i, suit_i) = self.card_position(card);
+                rank_sets[suit_i][round_i] |= 1 << rank_i;
+            }
+        }
+        indexer.index(&rank_sets)
+    }
+
+    pub fn unindex(&self, street: BettingStage, index: u64) -> (Vec<Card>, Vec<Card>) {
+        // Returns the canonical representative of the class, which uses the
+        // deck's suits in order of how many cards they hold.
+        let indexer = &self.streets[Self::street_i(street)];
+        let rank_sets = indexer.unindex(index);
+        let mut rounds: Vec<Vec<Card>> = vec![Vec::new(); indexer.n_rounds()];
+        for (suit_i, suit_rank_sets) in rank_sets.iter().enumerate() {
+            for (round_i, rank_set) in suit_rank_sets.iter().enumerate() {
+                for (rank_i, rank) in self.ranks.iter().enumerate() {
+                    if rank_set & (1 << rank_i) != 0 {
+                        rounds[round_i].push(Card::new(&rank.to_string(), self.suits[suit_i]));
+                    }
+                }
+            }
+        }
+        let hand = rounds.remove(0);
+        let board = rounds.into_iter().flatten().collect();
+        (hand, board)
+    }
+
+    pub fn canonicalise(&self, hand: &[Card], board: &[Card]) -> (Vec<Card>, Vec<Card>) {
+        let street = Self::street_from_board(board.len());
+        self.unindex(street, self.index(hand, board))
+    }
+
+    pub fn street_from_board(n_board_cards: usize) -> BettingStage {
+        match n_board_cards {
+            0 => BettingStage::PreFlop,
+            3 => BettingStage::Flop,
+            4 => BettingStage::Turn,
+            5 => BettingStage::River,
+            _ => panic!("A board has 0, 3, 4 or 5 cards, not {}", n_board_cards),
+        }
+    }
+
+    fn street_i(street: BettingStage) -> usize {
+        Self::STREETS.iter().position(|&s| s == street).unwrap()
+    }
+
+    fn card_position(&self, card: &Card) -> (usize, usize) {
+        let rank_i = self.ranks
+            .iter()
+            .position(|&rank| rank == card.rank_int())
+            .unwrap_or_else(|| panic!("{:?} is not in the deck", card));
+        let suit_i = self.suits
+            .iter()
+            .position(|&suit| suit == card.suit())
+            .unwrap_or_else(|| panic!("{:?} is not in the deck", card));
+        (rank_i, suit_i)
+    }
+}
+
+struct Configuration {
+    // Count vector of each canonical suit, in non-increasing order.
+    suit_counts: Vec<Vec<usize>>,
+    offset: u64,
+}
+
+struct StreetIndexer {
+    n_ranks: usize,
+    cards_per_round: Vec<usize>,
+    configurations: Vec<Configuration>,
+    configuration_lookup: HashMap<Vec<Vec<usize>>, usize>,
+    size: u64,
+}
+
+impl StreetIndexer {
+    fn new(n_ranks: usize, n_suits: usize, cards_per_round: &[usize]) -> Self {
+        let mut indexer = StreetIndexer {
+            n_ranks,
+            cards_per_round: cards_per_round.to_vec(),
+            configurations: Vec::new(),
+            configuration_lookup: HashMap::new(),
+            size: 0,
+        };
+
+        let count_vectors = indexer.suit_count_vectors();
+        let mut all_suit_counts = Vec::new();
+        indexer.enumerate_configurations(&count_vectors, 0, n_suits, &mut Vec::new(), &mut all_suit_counts);
+        for suit_counts in all_suit_counts {
+            let size: u64 = indexer.groups(&suit_counts)
+                .iter()
+                .map(|(start, len)| multiset_size(indexer.n_suit_rank_sets(&suit_counts[*start]), *len))
+                .product();
+            indexer.configuration_lookup.insert(suit_counts.clone(), indexer.configurations.len());
+            indexer.configurations.push(Configuration {
+                suit_counts,
+                offset: indexer.size,
+            });
+            indexer.size += size;
+        }
+        indexer
+    }
+
+    fn n_rounds(&self) -> usize {
+        self.cards_per_round.len()
+    }
+
+    fn index(&self, rank_sets: &[Vec<u16>]) -> u64 {
+        let mut suits: Vec<(Vec<usize>, u64)> = rank_sets
+            .iter()
+            .map(|suit_rank_sets| {
+                let counts = suit_rank_sets.iter().map(|set| set.count_ones() as usize).collect();
+                (counts, self.suit_index(suit_rank_sets))
+            })
+            .collect();
+        suits.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let suit_counts: Vec<Vec<usize>> = suits.iter().map(|(counts, _)| counts.clone()).collect();
+        let configuration = &self.configurations[self.configuration_lookup[&suit_counts]];
+        let mut index = 0;
+        for (start, len) in self.groups(&suit_counts) {
+            let n_rank_sets = self.n_suit_rank_sets(&suit_counts[start]);
+            let suit_indices: Vec<u64> = suits[start..start + len].iter().map(|(_, i)| *i).collect();
+            index = index * multiset_size(n_rank_sets, len) + multiset_index(&suit_indices);
+        }
+        configuration.offset + index
+    }
+
+    fn unindex(&self, index: u64) -> Vec<Vec<u16>> {
+        if index >= self.size {
+            panic!("Index {} is out of range, this street has {} hands", index, self.size);
+        }
+        let configuration_i = self.configurations.partition_point(|c| c.offset <= index) - 1;
+        let configuration = &self.configurations[configuration_i];
+        let mut index = index - configuration.offset;
+
+        let groups = self.groups(&configuration.suit_counts);
+        let mut rank_sets = vec![Vec::new(); configuration.suit_counts.len()];
+        for (start, len) in groups.into_iter().rev() {
+            let counts = &configuration.suit_counts[start];
+            let group_size = multiset_size(self.n_suit_rank_sets(counts), len);
+            let suit_indices = multiset_unindex(index % group_size, len);
+            index /= group_size;
+            for (suit_i, suit_index) in (start..start + len).zip(suit_indices) {
+                rank_sets[suit_i] = self.suit_unindex(counts, suit_index);
+            }
+        }
+        rank_sets
+    }
+
+    fn suit_index(&self, suit_rank_sets: &[u16]) -> u64 {
+        let mut used = 0u16;
+        let mut index = 0;
+        for &rank_set in suit_rank_sets {
+            let n_available = self.n_ranks - used.count_ones() as usize;
+            let positions: Vec<u64> = (0..self.n_ranks)
+                .filter(|rank_i| rank_set & (1 << rank_i) != 0)
+                .map(|rank_i| rank_i as u64 - (used & ((1 << rank_i) - 1)).count_ones() as u64)
+                .collect();
+            index = index * n_choose_k(n_available as u64, positions.len() as u64) + set_index(&positions);
+            used |= rank_set;
+        }
+        index
+    }
+
+    fn suit_unindex(&self, counts: &[usize], index: u64) -> Vec<u16> {
+        let mut n_available = Vec::new();
+        let mut n_used = 0;
+        for &count in counts {
+            n_available.push(self.n_ranks - n_used);
+            n_used += count;
+        }
+        let mut round_indices = vec![0; counts.len()];
+        let mut index = index;
+        for round_i in (0..counts.len()).rev() {
+            let n_sets = n_choose_k(n_available[round_i] as u64, counts[round_i] as u64);
+            round_indices[round_i] = index % n_sets;
+            index /= n_sets;
+        }
+
+        let mut used = 0u16;
+        let mut rank_sets = Vec::new();
+        for (round_i, &count) in counts.iter().enumerate() {
+            let unused: Vec<usize> = (0..self.n_ranks).filter(|rank_i| used & (1 << rank_i) == 0).collect();
+            let mut rank_set = 0u16;
+            for position in set_unindex(round_indices[round_i], count) {
+                rank_set |= 1 << unused[position as usize];
+            }
+            used |= rank_set;
+            rank_sets.push(rank_set);
+        }
+        rank_sets
+    }
+
+    fn n_suit_rank_sets(&self, counts: &[usize]) -> u64 {
+        let mut n_available = self.n_ranks as u64;
+        let mut n_sets = 1;
+        for &count in counts {
+            n_sets *= n_choose_k(n_available, count as u64);
+            n_available -= count as u64;
+        }
+        n_sets
+    }
+
+    fn groups(&self, suit_counts: &[Vec<usize>]) -> Vec<(usize, usize)> {
+        // Runs of suits with equal count vectors, as (start, len).
+        let mut groups: Vec<(usize, usize)> = Vec::new();
+        for (suit_i, counts) in suit_counts.iter().enumerate() {
+            match groups.last_mut() {
+                Some((start, len)) if suit_counts[*start] == *counts => *len += 1,
+                _ => groups.push((suit_i, 1)),
+            }
+        }
+        groups
+    }
+
+    fn suit_count_vectors(&self) -> Vec<Vec<usize>> {
+        // Every count vector a single suit can have, in decreasing order.
+        let mut count_vectors: Vec<Vec<usize>> = vec![Vec::new()];
+        for &n_cards in self.cards_per_round.iter() {
+            count_vectors = count_vectors
+                .into_iter()
+                .flat_map(|counts| (0..=n_cards).map(move |count| {
+                    let mut counts = counts.clone();
+                    counts.push(count);
+                    counts
+                }))
+                .filter(|counts| counts.iter().sum::<usize>() <= self.n_ranks)
+                .collect();
+        }
+        count_vectors.sort_by(|a, b| b.cmp(a));
+        count_vectors
+    }
+
+    fn enumerate_configurations(
+        &self,
+        count_vectors: &[Vec<usize>],
+        first_vector_i: usize,
+        n_suits_left: usize,
+        suit_counts: &mut Vec<Vec<usize>>,
+        configurations: &mut Vec<Vec<Vec<usize>>>,
+    ) {
+        // Choose count vectors in non-increasing order so that every
+        // configuration is produced once.
+        let totals: Vec<usize> = (0..self.n_rounds())
+            .map(|round_i| suit_counts.iter().map(|counts| counts[round_i]).sum())
+            .collect();
+        if n_suits_left == 0 {
+            if totals == self.cards_per_round {
+                configurations.push(suit_counts.clone());
+            }
+            return;
+        }
+        for (vector_i, counts) in count_vectors.iter().enumerate().skip(first_vector_i) {
+            let fits = (0..self.n_rounds())
+                .all(|round_i| totals[round_i] + counts[round_i] <= self.cards_per_round[round_i]);
+            if fits {
+                suit_counts.push(counts.clone());
+                self.enumerate_configurations(count_vectors, vector_i, n_suits_left - 1, suit_counts, configurations);
+                suit_counts.pop();
+            }
+        }
+    }
+}
+
+pub fn n_choose_k(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k as u128 {
+        result = result * (n as u128 - i) / (i + 1);
+    }
+    result as u64
+}
+
+fn set_index(positions: &[u64]) -> u64 {
+    // Colexicographic index of a set given as increasing positions.
+    positions
+        .iter()
+        .enumerate()
+        .map(|(k, &position)| n_choose_k(position, k as u64 + 1))
+        .sum()
+}
+
+fn set_unindex(index: u64, n_elements: usize) -> Vec<u64> {
+    let mut positions = vec![0; n_elements];
+    let mut index = index;
+    for k in (1..=n_elements as u64).rev() {
+        // Largest position p with C(p, k) <= index.
+        let (mut low, mut high) = (k - 1, k - 1);
+        while n_choose_k(high + 1, k) <= index {
+            high = (high + 1) * 2;
+        }
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if n_choose_k(mid, k) <= index {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        index -= n_choose_k(low, k);
+        positions[k as usize - 1] = low;
+    }
+    positions
+}
+
+fn multiset_size(n_values: u64, n_elements: usize) -> u64 {
+    n_choose_k(n_values + n_elements as u64 - 1, n_elements as u64)
+}
+
+fn multiset_index(values: &[u64]) -> u64 {
+    // Values must be sorted in increasing order, repeats allowed. Shifting
+    // the k-th value up by k turns the multiset into a set.
+    let positions: Vec<u64> = values.iter().enumerate().map(|(k, &v)| v + k as u64).collect();
+    set_index(&positions)
+}
+
+fn multiset_unindex(index: u64, n_elements: usize) -> Vec<u64> {
+    set_unindex(index, n_elements)
+        .into_iter()
+        .enumerate()
+        .map(|(k, position)| position - k as u64)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::{thread_rng, Rng};
+
+    use super::*;
+
+    fn random_deal(rng: &mut impl Rng, n_board_cards: usize) -> (Vec<Card>, Vec<Card>) {
+        let mut cards = Deck::new(None, None).all_cards();
+        cards.shuffle(rng);
+        (cards[..2].to_vec(), cards[2..2 + n_board_cards].to_vec())
+    }
+
+    fn with_suits(cards: &[Card], permutation: &[&'static str]) -> Vec<Card> {
+        cards
+            .iter()
+            .map(|card| {
+                let suit_i = Deck::DEFAULT_INCLUDE_SUITS.iter().position(|&suit| suit == card.suit()).unwrap();
+                Card::new(&card.rank_int().to_string(), permutation[suit_i])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sizes_of_full_deck_streets() {
+        let indexer = HandIndexer::new(&Deck::new(None, None));
+        let sizes: Vec<u64> = HandIndexer::STREETS.iter().map(|&street| indexer.size(street)).collect();
+        assert_eq!(sizes, [169, 1_286_792, 55_190_538, 2_428_287_420]);
+    }
+
+    #[test]
+    fn unindex_inverts_index() {
+        let indexer = HandIndexer::new(&Deck::new(None, None));
+        for index in 0..169 {
+            let (hand, board) = indexer.unindex(BettingStage::PreFlop, index);
+            assert_eq!(indexer.index(&hand, &board), index);
+        }
+        let mut rng = thread_rng();
+        for street in &HandIndexer::STREETS[1..] {
+            for _ in 0..1000 {
+                let index = rng.gen_range(0..indexer.size(*street));
+                let (hand, board) = indexer.unindex(*street, index);
+                assert_eq!(HandIndexer::street_from_board(board.len()), *street);
+                assert_eq!(indexer.index(&hand, &board), index);
+            }
+        }
+    }
+
+    #[test]
+    fn index_ignores_suit_names_and_flop_order() {
+        let indexer = HandIndexer::new(&Deck::new(None, None));
+        let mut rng = thread_rng();
+        for n_board_cards in [0, 3, 4, 5] {
+            for _ in 0..500 {
+                let (hand, board) = random_deal(&mut rng, n_board_cards);
+                let index = indexer.index(&hand, &board);
+
+                let mut permutation = Deck::DEFAULT_INCLUDE_SUITS;
+                permutation.shuffle(&mut rng);
+                assert_eq!(indexer.index(&with_suits(&hand, &permutation), &with_suits(&board, &permutation)), index);
+
+                let (mut reordered_hand, mut reordered_board) = (hand.clone(), board.clone());
+                reordered_hand.reverse();
+                reordered_board[..n_board_cards.min(3)].shuffle(&mut rng);
+                assert_eq!(indexer.index(&reordered_hand, &reordered_board), index);
+            }
+        }
+    }
+
+    #[test]
+    fn index_tells_rounds_apart() {
+        // The same seven cards with the turn and a flop card swapped are a
+        // different hand.
+        let indexer = HandIndexer::new(&Deck::new(None, None));
+        let hand = [Card::new("A", "spades"), Card::new("K", "spades")];
+        let board = ["2", "7", "9", "Q"].map(|rank| Card::new(rank, "hearts"));
+        let swapped = [board[3].clone(), board[1].clone(), board[2].clone(), board[0].clone()];
+        assert_ne!(indexer.index(&hand, &board), indexer.index(&hand, &swapped));
+    }
+}
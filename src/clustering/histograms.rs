@@ -0,0 +1,139 @@
+/// Equity features for card abstraction. A hand's river equity is its share of
+/// the pot against one random opponent hand; on earlier streets a hand is
+/// described by the histogram of the river equities it can end up with, so
+/// that hands with the same average equity but different potential (a draw
+/// versus a medium pair) land in different buckets.
+use itertools::Itertools;
+use rand::seq::index::sample;
+use rand::thread_rng;
+
+use crate::poker::card::Card;
+use crate::poker::evaluation::evaluator::Evaluator;
+
+pub fn river_equity(
+    evaluator: &Evaluator,
+    deck_cards: &[Card],
+    hand: &[Card],
+    board: &[Card],
+    n_opponent_samples: usize,
+) -> f64 {
+    // Wins count 1 and ties 1/2. With `n_opponent_samples` 0 every opponent
+    // hand is enumerated, otherwise that many are drawn at random.
+    let hand = hand.to_vec();
+    let board = board.to_vec();
+    let remaining = remaining_cards(deck_cards, &[&hand, &board]);
+    let hero_rank = evaluator.evaluate(&hand, &board);
+
+    let opponent_hands: Vec<[Card; 2]> = if n_opponent_samples == 0 {
+        remaining.iter().cloned().tuple_combinations().map(|(a, b)| [a, b]).collect()
+    } else {
+        let mut rng = thread_rng();
+        (0..n_opponent_samples)
+            .map(|_| {
+                let pair = sample(&mut rng, remaining.len(), 2);
+                [remaining[pair.index(0)].clone(), remaining[pair.index(1)].clone()]
+            })
+            .collect()
+    };
+
+    let mut score = 0.0;
+    for opponent_rank in evaluator.evaluate_batch(&board, &opponent_hands) {
+        if hero_rank < opponent_rank {
+            score += 1.0;
+        } else if hero_rank == opponent_rank {
+            score += 0.5;
+        }
+    }
+    score / opponent_hands.len() as f64
+}
+
+pub fn equity_histogram(
+    evaluator: &Evaluator,
+    deck_cards: &[Card],
+    hand: &[Card],
+    board: &[Card],
+    n_bins: usize,
+    n_runouts: usize,
+    n_opponent_samples: usize,
+) -> Vec<f64> {
+    // Normalised histogram of river equity over the ways the board can be
+    // completed. With `n_runouts` 0 every runout is enumerated, otherwise
+    // that many are drawn at random.
+    let remaining = remaining_cards(deck_cards, &[hand, board]);
+    let n_cards_to_come = 5 - board.len();
+
+    let runouts: Vec<Vec<Card>> = if n_runouts == 0 {
+        remaining.iter().cloned().combinations(n_cards_to_come).collect()
+    } else {
+        let mut rng = thread_rng();
+        (0..n_runouts)
+            .map(|_| {
+                sample(&mut rng, remaining.len(), n_cards_to_come)
+                    .into_iter()
+                    .map(|i| remaining[i].clone())
+                    .collect()
+            })
+            .collect()
+    };
+
+    let mut histogram = vec![0.0; n_bins];
+    for runout in runouts.iter() {
+        let river_board: Vec<Card> = board.iter().chain(runout.iter()).cloned().collect();
+        let equity = river_equity(evaluator, deck_cards, hand, &river_board, n_opponent_samples);
+        let bin = ((equity * n_bins as f64) as usize).min(n_bins - 1);
+        histogram[bin] += 1.0;
+    }
+    histogram.iter_mut().for_each(|count| *count /= runouts.len() as f64);
+    histogram
+}
+
+fn remaining_cards(deck_cards: &[Card], used: &[&[Card]]) -> Vec<Card> {
+    deck_cards
+        .iter()
+        .filter(|card| !used.iter().any(|cards| cards.contains(card)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::deck::Deck;
+
+    fn cards(cards: &[(&str, &str)]) -> Vec<Card> {
+        cards.iter().map(|&(rank, suit)| Card::new(rank, suit)).collect()
+    }
+
+    #[test]
+    fn river_equity_of_the_nuts_and_a_chopped_board() {
+        let evaluator = Evaluator::new();
+        let deck = Deck::new(None, None).all_cards();
+        let royal_flush = cards(&[("A", "spades"), ("K", "spades")]);
+        let board = cards(&[("Q", "spades"), ("J", "spades"), ("10", "spades"), ("2", "hearts"), ("3", "diamonds")]);
+        assert_eq!(river_equity(&evaluator, &deck, &royal_flush, &board, 0), 1.0);
+
+        // A royal flush on the board ties every opponent.
+        let hand = cards(&[("2", "clubs"), ("3", "clubs")]);
+        let board = cards(&[("A", "hearts"), ("K", "hearts"), ("Q", "hearts"), ("J", "hearts"), ("10", "hearts")]);
+        assert_eq!(river_equity(&evaluator, &deck, &hand, &board, 0), 0.5);
+    }
+
+    #[test]
+    fn histogram_over_turn_runouts() {
+        let evaluator = Evaluator::new();
+        let deck = Deck::new(None, None).all_cards();
+        let board = cards(&[("Q", "spades"), ("J", "spades"), ("10", "spades"), ("2", "hearts")]);
+
+        let royal_flush = cards(&[("A", "spades"), ("K", "spades")]);
+        let histogram = equity_histogram(&evaluator, &deck, &royal_flush, &board, 10, 0, 20);
+        assert_eq!(histogram[9], 1.0);
+
+        // An open-ended straight draw with no pair: most rivers leave it far
+        // behind, and the kings and eights make it a straight.
+        let draw = cards(&[("9", "hearts"), ("4", "clubs")]);
+        let histogram = equity_histogram(&evaluator, &deck, &draw, &board, 10, 0, 0);
+        assert!((histogram.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(histogram[..5].iter().sum::<f64>() > 0.5);
+        assert!(histogram[7..].iter().sum::<f64>() > 0.0);
+    }
+}
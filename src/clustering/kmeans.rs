@@ -0,0 +1,163 @@
+/// K-means over equity histograms, using the earth mover's distance as in
+/// Johanson et al., "Evaluating State-Space Abstractions in Extensive-Form
+/// Games" (2013). For one-dimensional histograms the earth mover's distance
+/// is the L1 distance between the cumulative distributions, so it can be
+/// computed in a single pass. Centroids are the mean of their histograms.
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{thread_rng, Rng};
+
+pub struct KMeans {
+    pub n_clusters: usize,
+    pub max_iterations: usize,
+}
+
+pub struct Clustering {
+    pub assignments: Vec<u32>,
+    pub centroids: Vec<Vec<f64>>,
+}
+
+impl Clustering {
+    pub fn nearest(&self, point: &[f64]) -> u32 {
+        // The cluster of a point that was not part of the fit.
+        nearest_centroid(point, &self.centroids).0 as u32
+    }
+}
+
+impl KMeans {
+    pub fn new(n_clusters: usize, max_iterations: usize) -> Self {
+        KMeans {
+            n_clusters,
+            max_iterations,
+        }
+    }
+
+    pub fn fit(&self, points: &[Vec<f64>]) -> Clustering {
+        let mut centroids = self.initial_centroids(points);
+        let mut assignments = vec![0u32; points.len()];
+        for iteration in 0..self.max_iterations {
+            let mut n_changed = 0;
+            for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+                let nearest = nearest_centroid(point, &centroids).0 as u32;
+                if nearest != *assignment || iteration == 0 {
+                    n_changed += 1;
+                    *assignment = nearest;
+                }
+            }
+            if n_changed == 0 {
+                break;
+            }
+            centroids = self.update_centroids(points, &assignments, &centroids);
+        }
+        Clustering {
+            assignments,
+            centroids,
+        }
+    }
+
+    fn initial_centroids(&self, points: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        // k-means++: each new centroid is drawn with probability proportional
+        // to its squared distance from the nearest existing one.
+        let mut rng = thread_rng();
+        let mut centroids = vec![points[rng.gen_range(0..points.len())].clone()];
+        while centroids.len() < self.n_clusters.min(points.len()) {
+            let weights: Vec<f64> = points
+                .iter()
+                .map(|point| nearest_centroid(point, &centroids).1.powi(2))
+                .collect();
+            match WeightedIndex::new(&weights) {
+                Ok(distribution) => centroids.push(points[distribution.sample(&mut rng)].clone()),
+                // Every point already sits on a centroid.
+                Err(_) => break,
+            }
+        }
+        centroids
+    }
+
+    fn update_centroids(&self, points: &[Vec<f64>], assignments: &[u32], previous: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        let n_dims = points[0].len();
+        let mut sums = vec![vec![0.0; n_dims]; previous.len()];
+        let mut counts = vec![0usize; previous.len()];
+        for (point, &assignment) in points.iter().zip(assignments) {
+            counts[assignment as usize] += 1;
+            for (total, value) in sums[assignment as usize].iter_mut().zip(point) {
+                *total += value;
+            }
+        }
+        sums.into_iter()
+            .zip(counts)
+            .zip(previous)
+            .map(|((sum, count), centroid)| {
+                if count == 0 {
+                    // Keep an empty cluster where it was rather than dropping it.
+                    centroid.clone()
+                } else {
+                    sum.into_iter().map(|total| total / count as f64).collect()
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn earth_movers_distance(a: &[f64], b: &[f64]) -> f64 {
+    let mut distance = 0.0;
+    let mut carried = 0.0;
+    for (a_i, b_i) in a.iter().zip(b) {
+        carried += a_i - b_i;
+        distance += f64::abs(carried);
+    }
+    distance
+}
+
+fn nearest_centroid(point: &[f64], centroids: &[Vec<f64>]) -> (usize, f64) {
+    centroids
+        .iter()
+        .map(|centroid| earth_movers_distance(point, centroid))
+        .enumerate()
+        .fold((0, f64::INFINITY), |best, (i, distance)| if distance < best.1 { (i, distance) } else { best })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earth_movers_distance_moves_mass() {
+        assert_eq!(earth_movers_distance(&[1.0, 0.0, 0.0], &[0.0, 0.0, 1.0]), 2.0);
+        assert_eq!(earth_movers_distance(&[0.5, 0.5, 0.0], &[0.0, 0.5, 0.5]), 1.0);
+        assert_eq!(earth_movers_distance(&[0.2, 0.3, 0.5], &[0.2, 0.3, 0.5]), 0.0);
+    }
+
+    #[test]
+    fn recovers_separated_clusters() {
+        // Histograms with their mass near the first, middle or last of ten
+        // bins. The clusters are far enough apart that k-means++ all but
+        // surely seeds one centroid in each.
+        let mut rng = thread_rng();
+        let mut points = Vec::new();
+        let mut labels = Vec::new();
+        for _ in 0..60 {
+            let label = rng.gen_range(0..3);
+            let mut histogram = vec![0.0; 10];
+            let peak = [0, 5, 9][label];
+            let spill: f64 = rng.gen_range(0.0..0.05);
+            histogram[peak] = 1.0 - spill;
+            histogram[if peak == 9 { 8 } else { peak + 1 }] = spill;
+            points.push(histogram);
+            labels.push(label);
+        }
+
+        let clustering = KMeans::new(3, 100).fit(&points);
+        assert_eq!(clustering.centroids.len(), 3);
+        // Each label's points share a cluster, and no two labels do.
+        let clusters: Vec<u32> = (0..3)
+            .map(|label| clustering.assignments[labels.iter().position(|&l| l == label).unwrap()])
+            .collect();
+        for (&assignment, &label) in clustering.assignments.iter().zip(&labels) {
+            assert_eq!(assignment, clusters[label]);
+        }
+        assert!(clusters[0] != clusters[1] && clusters[1] != clusters[2] && clusters[0] != clusters[2]);
+        let mut peak = vec![0.0; 10];
+        peak[5] = 1.0;
+        assert_eq!(clustering.nearest(&peak), clusters[1]);
+    }
+}
@@ -1,3 +1,6 @@
+pub mod bytes;
+pub mod cfr;
+pub mod clustering;
 pub mod poker;
 
 
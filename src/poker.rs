@@ -1,9 +1,16 @@
 pub mod evaluation;
+pub mod action_abstraction;
+pub mod action_translation;
 pub mod actions;
 pub mod card;
 pub mod deck;
+pub mod equity;
 pub mod game;
+pub mod info_set;
 pub mod player;
-pub mod pot;
+pub mod range;
 pub mod state;
+pub mod strategy;
+pub mod table;
+pub mod toy_games;
 pub mod engine;
\ No newline at end of file
@@ -0,0 +1,203 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+
+use super::actions::{legal_actions, Action};
+use super::state::{BettingStage, PokerGameState};
+
+/// A bet or raise size in the action abstraction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RaiseSize {
+    // A fraction of the pot after calling: 1.0 facing a bet of 100 with 400
+    // chips in the pot raises to 100 + (400 + 100).
+    PotFraction(f64),
+    AllIn,
+}
+
+/// The bet and raise sizes the blueprint considers, for each street and each
+/// number of raises already made in the betting round. A raise depth past the
+/// last one configured for a street allows no more raises, which keeps the
+/// game tree finite. Sizes below the minimum raise are rounded up to it and
+/// sizes above the stack become all in.
+///
+/// Configs can be read from text, one line per street and raise depth:
+/// ```text
+/// # street depth sizes...
+/// preflop 0 0.5 1 allin
+/// preflop 1 1 allin
+/// flop 0 0.5 1 allin
+/// ```
+/// Depths must be listed in order from 0 for each street, each with at least
+/// one size, and a street that is not listed allows no raises. A config must
+/// list at least one line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionAbstraction {
+    raise_sizes: [Vec<Vec<RaiseSize>>; 4],
+}
+
+impl ActionAbstraction {
+    pub fn new(raise_sizes: [Vec<Vec<RaiseSize>>; 4]) -> Self {
+        ActionAbstraction { raise_sizes }
+    }
+
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let mut raise_sizes: [Vec<Vec<RaiseSize>>; 4] = Default::default();
+        for (line_i, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("Line {}: {} in '{}'", line_i + 1, message, line);
+            let mut fields = line.split_whitespace();
+            let street_i = match fields.next().unwrap() {
+                "preflop" => 0,
+                "flop" => 1,
+                "turn" => 2,
+                "river" => 3,
+                _ => return Err(error("unknown street")),
+            };
+            let depth: usize = fields
+                .next()
+                .and_then(|depth| depth.parse().ok())
+                .ok_or_else(|| error("missing raise depth"))?;
+            if depth != raise_sizes[street_i].len() {
+                return Err(error("raise depths out of order"));
+            }
+            let sizes = fields
+                .map(|size| match size {
+                    "allin" => Ok(RaiseSize::AllIn),
+                    _ => match size.parse::<f64>() {
+                        Ok(fraction) if fraction > 0.0 && fraction.is_finite() => Ok(RaiseSize::PotFraction(fraction)),
+                        _ => Err(error("invalid raise size")),
+                    },
+                })
+                .collect::<Result<Vec<RaiseSize>, String>>()?;
+            if sizes.is_empty() {
+                return Err(error("no raise sizes"));
+            }
+            raise_sizes[street_i].push(sizes);
+        }
+        if raise_sizes.iter().all(Vec::is_empty) {
+            return Err("The action abstraction config has no raise sizes".to_string());
+        }
+        Ok(ActionAbstraction { raise_sizes })
+    }
+
+    pub fn load(path: &str) -> std::io::Result<Self> {
+        let config = fs::read_to_string(path)?;
+        ActionAbstraction::parse(&config).map_err(|message| Error::new(ErrorKind::InvalidData, message))
+    }
+
+    pub fn raise_sizes(&self, betting_stage: BettingStage, n_raises: u32) -> &[RaiseSize] {
+        let street_i = betting_stage as usize;
+        match self.raise_sizes[street_i].get(n_raises as usize) {
+            Some(sizes) => sizes,
+            None => &[],
+        }
+    }
+
+    pub fn legal_actions(&self, state: &PokerGameState) -> Vec<Action> {
+        // Fold when facing a bet, check or call, then the abstract raises in
+        // the order configured with duplicates removed.
+        let legal = legal_actions(state);
+        let mut actions = Vec::new();
+        if legal.fold {
+            actions.push(Action::Fold);
+        }
+        if legal.check {
+            actions.push(Action::Check);
+        } else if legal.call.is_some() {
+            actions.push(Action::Call);
+        }
+        if let Some(raise) = legal.raise {
+            let street_bet = state.street_bet();
+            let n_chips_to_call = legal.call.unwrap_or_default();
+            let pot = state.table().pot() + n_chips_to_call;
+            for size in self.raise_sizes(state.betting_stage(), state.n_raises()) {
+                let n_chips = match size {
+                    RaiseSize::PotFraction(fraction) => street_bet + (fraction * pot as f64).round() as i32,
+                    RaiseSize::AllIn => raise.max_to,
+                };
+                let n_chips = n_chips.clamp(raise.min_to, raise.max_to);
+                let action = if n_chips == raise.max_to { Action::AllIn } else { state.raise_action(n_chips) };
+                if !actions.contains(&action) {
+                    actions.push(action);
+                }
+            }
+        }
+        actions
+    }
+}
+
+impl Default for ActionAbstraction {
+    fn default() -> Self {
+        // Half pot, pot and all in to open, pot and all in over one raise,
+        // and only all in after that, on every street.
+        let sizes = vec![
+            vec![RaiseSize::PotFraction(0.5), RaiseSize::PotFraction(1.0), RaiseSize::AllIn],
+            vec![RaiseSize::PotFraction(1.0), RaiseSize::AllIn],
+            vec![RaiseSize::AllIn],
+        ];
+        ActionAbstraction::new([sizes.clone(), sizes.clone(), sizes.clone(), sizes])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::poker::deck::Deck;
+
+    #[test]
+    fn parse_config() {
+        let config = "# street depth sizes...\npreflop 0 0.5 1 allin\n\npreflop 1 allin  # then shove\nriver 0 0.75\n";
+        let abstraction = ActionAbstraction::parse(config).unwrap();
+        assert_eq!(
+            abstraction.raise_sizes(BettingStage::PreFlop, 0),
+            [RaiseSize::PotFraction(0.5), RaiseSize::PotFraction(1.0), RaiseSize::AllIn]
+        );
+        assert_eq!(abstraction.raise_sizes(BettingStage::PreFlop, 1), [RaiseSize::AllIn]);
+        assert!(abstraction.raise_sizes(BettingStage::PreFlop, 2).is_empty());
+        assert!(abstraction.raise_sizes(BettingStage::Flop, 0).is_empty());
+        assert_eq!(abstraction.raise_sizes(BettingStage::River, 0), [RaiseSize::PotFraction(0.75)]);
+    }
+
+    #[test]
+    fn parse_rejects_bad_configs() {
+        let configs = [
+            "",
+            "# only a comment\n",
+            "preflop 0 -0.5",
+            "preflop 0 0",
+            "preflop 0 inf",
+            "preflop 0 half",
+            "preflop 0 1 all-in",
+            "preflop 0",
+            "preflop",
+            "preflop x 1",
+            "showdown 0 1",
+            "preflop 1 1",
+            "preflop 0 1\npreflop 0 allin",
+        ];
+        for config in configs {
+            assert!(ActionAbstraction::parse(config).is_err(), "{:?} parsed", config);
+        }
+        let error = ActionAbstraction::parse("flop 0 1\nturn 0 -1").unwrap_err();
+        assert!(error.starts_with("Line 2: invalid raise size"), "{}", error);
+    }
+
+    #[test]
+    fn sizes_become_legal_raises() {
+        // Heads up with blinds of 50 and 100, the small blind faces a pot of
+        // 200 after calling, so half pot raises to 200 and 1.25 pots to 350.
+        // A size under the minimum raise is rounded up to it.
+        let abstraction = ActionAbstraction::parse("preflop 0 0.1 0.5 1.25 allin").unwrap();
+        let mut state = PokerGameState::new_hand(&[1000, 1000], &Deck::new(None, None), 50, 100);
+        state.set_action_abstraction(Arc::new(abstraction));
+        let state = state.sample_chance_outcome();
+        assert_eq!(
+            state.legal_actions(),
+            [Action::Fold, Action::Call, Action::RaiseTo(200), Action::RaiseTo(350), Action::AllIn]
+        );
+    }
+}
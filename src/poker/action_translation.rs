@@ -0,0 +1,136 @@
+/// Action translation: maps a bet of any size onto the bet sizes of the
+/// action abstraction, so that a blueprint trained on a few sizes can
+/// interpret real play. Bet sizes are compared as fractions of the pot after
+/// calling, and a size between two abstract ones is mapped to one of them at
+/// random with the pseudo-harmonic mapping of Ganzfried and Sandholm, "Action
+/// Translation in Extensive-Form Games with Large Action Spaces" (2013).
+use rand::{thread_rng, Rng};
+
+use super::actions::{legal_actions, Action};
+use super::state::PokerGameState;
+
+pub fn pseudo_harmonic_probability(a: f64, b: f64, x: f64) -> f64 {
+    // The probability of mapping a size x, with a <= x <= b, down to a.
+    if b <= a {
+        return 1.0;
+    }
+    (((b - x) * (1.0 + a)) / ((b - a) * (1.0 + x))).clamp(0.0, 1.0)
+}
+
+pub fn translate(state: &PokerGameState, action: &Action) -> Action {
+    // Actions in the state's abstraction map to themselves. A bet or raise
+    // maps to the abstract bets and raises around it, or to calling if the
+    // abstraction allows none, and an all in that can not raise is a call.
+    let abstract_actions = state.legal_actions();
+    if abstract_actions.contains(action) {
+        return *action;
+    }
+    let legal = legal_actions(state);
+    let check_or_call = if legal.check { Action::Check } else { Action::Call };
+    let n_chips = match (action, legal.raise) {
+        (Action::Bet(n_chips), _) | (Action::RaiseTo(n_chips), _) => *n_chips,
+        (Action::AllIn, Some(raise)) => raise.max_to,
+        _ => return check_or_call,
+    };
+
+    let street_bet = state.street_bet();
+    let pot = state.table().pot() + legal.call.unwrap_or_default();
+    let pot_fraction = |n_chips: i32| (n_chips - street_bet) as f64 / pot as f64;
+    let mut raises: Vec<(f64, Action)> = abstract_actions
+        .iter()
+        .filter_map(|abstract_action| {
+            let n_chips = match (abstract_action, legal.raise) {
+                (Action::Bet(n_chips), _) | (Action::RaiseTo(n_chips), _) => *n_chips,
+                (Action::AllIn, Some(raise)) => raise.max_to,
+                _ => return None,
+            };
+            Some((pot_fraction(n_chips), *abstract_action))
+        })
+        .collect();
+    if raises.is_empty() {
+        return check_or_call;
+    }
+    raises.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let x = pot_fraction(n_chips);
+    let above_i = raises.iter().position(|(fraction, _)| *fraction >= x);
+    match above_i {
+        Some(0) => raises[0].1,
+        None => raises.last().unwrap().1,
+        Some(above_i) => {
+            let (a, smaller) = raises[above_i - 1];
+            let (b, bigger) = raises[above_i];
+            if thread_rng().gen::<f64>() < pseudo_harmonic_probability(a, b, x) {
+                smaller
+            } else {
+                bigger
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::poker::action_abstraction::ActionAbstraction;
+    use crate::poker::deck::Deck;
+
+    fn small_blind_to_act() -> PokerGameState {
+        // Heads up with blinds of 50 and 100, where the small blind's
+        // abstract raises are to 200 and 350, half and 1.25 times the pot.
+        let abstraction = ActionAbstraction::parse("preflop 0 0.5 1.25").unwrap();
+        let mut state = PokerGameState::new_hand(&[1000, 1000], &Deck::new(None, None), 50, 100);
+        state.set_action_abstraction(Arc::new(abstraction));
+        state.sample_chance_outcome()
+    }
+
+    #[test]
+    fn pseudo_harmonic_boundaries() {
+        for (a, b) in [(0.5, 1.0), (0.25, 2.0), (1.0, 4.0)] {
+            assert_eq!(pseudo_harmonic_probability(a, b, a), 1.0);
+            assert_eq!(pseudo_harmonic_probability(a, b, b), 0.0);
+        }
+        // Between half pot and pot, 3/4 pot maps down with probability 3/7.
+        assert!((pseudo_harmonic_probability(0.5, 1.0, 0.75) - 3.0 / 7.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn abstract_sizes_map_to_themselves() {
+        let state = small_blind_to_act();
+        for action in [Action::Fold, Action::Call, Action::RaiseTo(200), Action::RaiseTo(350)] {
+            for _ in 0..100 {
+                assert_eq!(translate(&state, &action), action);
+            }
+        }
+    }
+
+    #[test]
+    fn size_between_abstract_sizes() {
+        // Raising to 300 is a pot sized raise, x = 1, between a = 0.5 and
+        // b = 1.25, so it maps to 200 with probability 1/4 and to 350 otherwise.
+        let state = small_blind_to_act();
+        let n_samples = 20_000;
+        let mut n_down = 0;
+        for _ in 0..n_samples {
+            match translate(&state, &Action::RaiseTo(300)) {
+                Action::RaiseTo(200) => n_down += 1,
+                Action::RaiseTo(350) => {}
+                action => panic!("RaiseTo(300) translated to {}", action),
+            }
+        }
+        assert!((n_down as f64 / n_samples as f64 - 0.25).abs() < 0.02, "{} of {}", n_down, n_samples);
+    }
+
+    #[test]
+    fn sizes_outside_the_abstraction() {
+        // Bigger raises map to the biggest abstract one, and with no abstract
+        // raises left a raise becomes a call.
+        let state = small_blind_to_act();
+        assert_eq!(translate(&state, &Action::RaiseTo(800)), Action::RaiseTo(350));
+        assert_eq!(translate(&state, &Action::AllIn), Action::RaiseTo(350));
+        let state = state.apply_action(&Action::RaiseTo(200));
+        assert_eq!(translate(&state, &Action::RaiseTo(600)), Action::Call);
+    }
+}
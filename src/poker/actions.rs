@@ -1,83 +1,224 @@
-pub const DUMMY_AMOUNTS: [i32; 6] = [10, 100, 500, 1000, 5000, 10000];
+use super::state::PokerGameState;
 
-pub trait Action {}
+/// A betting action. Amounts are the player's total bet for the betting round
+/// after the action, so a player who raises a bet of 100 with `RaiseTo(300)`
+/// has 300 chips in front of them, not 400. `Bet` opens the betting on a
+/// street and `RaiseTo` raises an existing bet - pre-flop the blinds count as
+/// a bet. `AllIn` is a raise with the whole stack, or a call when the stack
+/// does not cover more.
+///
+/// Actions are written as "fold", "check", "call", "bet 300", "raise to 300"
+/// and "all in", and parse back from the same text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Fold,
+    Check,
+    Call,
+    Bet(i32),
+    RaiseTo(i32),
+    AllIn,
+}
 
-#[derive(Debug)]
-pub struct Call;
-impl Action for Call {}
-impl std::fmt::Display for Call {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "call")
+impl Action {
+    pub fn code(&self) -> String {
+        // A short form for betting sequences: f, k, c and a for fold, check,
+        // call and all in, and b or r followed by the amount for bets and
+        // raises, e.g. "b150r450c".
+        match self {
+            Action::Fold => "f".to_string(),
+            Action::Check => "k".to_string(),
+            Action::Call => "c".to_string(),
+            Action::Bet(n_chips) => format!("b{}", n_chips),
+            Action::RaiseTo(n_chips) => format!("r{}", n_chips),
+            Action::AllIn => "a".to_string(),
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct Fold;
-impl Action for Fold {}
-impl std::fmt::Display for Fold {
+impl std::fmt::Display for Action {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "fold")
+        match self {
+            Action::Fold => write!(f, "fold"),
+            Action::Check => write!(f, "check"),
+            Action::Call => write!(f, "call"),
+            Action::Bet(n_chips) => write!(f, "bet {}", n_chips),
+            Action::RaiseTo(n_chips) => write!(f, "raise to {}", n_chips),
+            Action::AllIn => write!(f, "all in"),
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_amount = |n_chips: &str| {
+            n_chips.parse::<i32>().map_err(|_| format!("Invalid amount '{}' in action '{}'", n_chips, s))
+        };
+        match s {
+            "fold" => Ok(Action::Fold),
+            "check" => Ok(Action::Check),
+            "call" => Ok(Action::Call),
+            "all in" => Ok(Action::AllIn),
+            _ => {
+                if let Some(n_chips) = s.strip_prefix("bet ") {
+                    Ok(Action::Bet(parse_amount(n_chips)?))
+                } else if let Some(n_chips) = s.strip_prefix("raise to ") {
+                    Ok(Action::RaiseTo(parse_amount(n_chips)?))
+                } else {
+                    Err(format!("Unknown action '{}'", s))
+                }
+            }
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct Raise {
-    amount:i32
+/// The actions open to the player to act. Amounts are chips bet this betting
+/// round: `call` is what calling costs, which is the player's whole stack
+/// when they can only call all in for less, and a raise can take the player's
+/// bet for the round to anything in `raise`. The top of that range is all in,
+/// as is the bottom when the stack is too short for a full raise.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LegalActions {
+    pub fold: bool,
+    pub check: bool,
+    pub call: Option<i32>,
+    pub raise: Option<RaiseRange>,
 }
-impl Action for Raise {}
-impl std::fmt::Display for Raise {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "raise")
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RaiseRange {
+    pub min_to: i32,
+    pub max_to: i32,
+}
+
+impl RaiseRange {
+    pub fn contains(&self, n_chips: i32) -> bool {
+        self.min_to <= n_chips && n_chips <= self.max_to
     }
 }
-impl Raise {
-    pub fn new() -> Self {
-        Raise{
-            amount: 0
-        }
+
+pub fn legal_actions(state: &PokerGameState) -> LegalActions {
+    // No-limit rules: a raise must be at least as big as the last full raise
+    // of the round, and a player who has acted may only raise again after a
+    // full raise, so an all in for less does not reopen the betting to them.
+    if !state.is_decision_node() {
+        return LegalActions::default();
     }
+    let table = state.table();
+    let player_i = state.player_i();
+    let n_chips = table.stacks[player_i];
+    let n_bet_chips = table.bets[player_i];
+    let biggest_bet = table.biggest_bet();
+    let n_chips_to_call = biggest_bet - n_bet_chips;
+
+    // Raising only makes sense with someone left who can call it.
+    let opponents_with_chips = (0..table.n_players())
+        .filter(|&other_i| other_i != player_i && table.is_active(other_i) && !table.is_all_in(other_i))
+        .count();
+    let raise = if state.can_raise(player_i) && n_chips > n_chips_to_call && opponents_with_chips > 0 {
+        let street_bet = n_bet_chips - state.street_start_bet();
+        let max_to = street_bet + n_chips;
+        let min_to = biggest_bet - state.street_start_bet() + state.min_raise();
+        Some(RaiseRange {
+            min_to: min_to.min(max_to),
+            max_to,
+        })
+    } else {
+        None
+    };
 
-    pub fn set_amount(&mut self, amount: i32) {
-        self.amount = amount;
+    LegalActions {
+        fold: n_chips_to_call > 0,
+        check: n_chips_to_call == 0,
+        call: if n_chips_to_call > 0 { Some(n_chips_to_call.min(n_chips)) } else { None },
+        raise,
     }
 }
 
-#[derive(Debug)]
-struct AbstractedRaise {
-    amounts: Vec<i32>,
-    amount: i32,
-}
-impl Action for AbstractedRaise {}
-impl AbstractedRaise {
-    pub fn new(allowed_amounts: Vec<i32>) -> Self {
-        AbstractedRaise {
-            amounts: allowed_amounts,
-            amount: 0,
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::deck::Deck;
+    use crate::poker::state::BettingStage;
+
+    fn dealt_hand(stacks: &[i32]) -> PokerGameState {
+        // Blinds of 1 and 2 in seats 0 and 1.
+        PokerGameState::new_hand(stacks, &Deck::new(None, None), 1, 2).sample_chance_outcome()
     }
 
-    fn call(&mut self, amount: i32) -> Result<(), String> {
-        if !self.amounts.contains(&amount) {
-            Err(format!(
-                "Specified amount '{}' is not valid for this action \
-                 abstraction, check 'allowed_amounts()' for more information",
-                amount
-            ))
-        } else {
-            self.amount = amount;
-            Ok(())
-        }
+    fn play(state: PokerGameState, actions: &[Action]) -> PokerGameState {
+        actions.iter().fold(state, |state, action| state.apply_action(action))
     }
-}
 
-impl std::fmt::Display for AbstractedRaise {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "raise {}", self.amount)
+    #[test]
+    fn big_blind_option() {
+        // Everyone limps, and the big blind can check or raise to 2 big blinds.
+        let state = play(dealt_hand(&[200, 200, 200]), &[Action::Call, Action::Call]);
+        assert_eq!(state.player_i(), 1);
+        assert_eq!(
+            legal_actions(&state),
+            LegalActions {
+                fold: false,
+                check: true,
+                call: None,
+                raise: Some(RaiseRange { min_to: 4, max_to: 200 }),
+            }
+        );
+        let state = state.apply_action(&Action::Check);
+        assert_eq!(state.betting_stage(), BettingStage::Flop);
+    }
+
+    #[test]
+    fn heads_up_acting_order() {
+        // The small blind is the button: first pre-flop, last on later streets.
+        let state = dealt_hand(&[100, 100]);
+        assert_eq!(state.player_i(), 0);
+        assert_eq!(legal_actions(&state).call, Some(1));
+        let state = play(state, &[Action::Call, Action::Check]).sample_chance_outcome();
+        assert_eq!(state.betting_stage(), BettingStage::Flop);
+        assert_eq!(state.player_i(), 1);
+        let state = state.apply_action(&Action::Check);
+        assert_eq!(state.player_i(), 0);
+    }
+
+    #[test]
+    fn min_raise_after_raises() {
+        // A raise must be at least as big as the last full raise.
+        let state = play(dealt_hand(&[200, 200, 200]), &[Action::RaiseTo(6)]);
+        assert_eq!(legal_actions(&state).raise, Some(RaiseRange { min_to: 10, max_to: 200 }));
+        assert!(!state.is_legal(&Action::RaiseTo(9)));
+        let state = state.apply_action(&Action::RaiseTo(20));
+        assert_eq!(legal_actions(&state).raise, Some(RaiseRange { min_to: 34, max_to: 200 }));
+
+        // On later streets the minimum bet is the big blind.
+        let state = play(dealt_hand(&[200, 200, 200]), &[Action::Call, Action::Call, Action::Check]);
+        let state = state.sample_chance_outcome();
+        assert_eq!(legal_actions(&state).raise, Some(RaiseRange { min_to: 2, max_to: 198 }));
+        let state = state.apply_action(&Action::Bet(10));
+        assert_eq!(legal_actions(&state).raise, Some(RaiseRange { min_to: 20, max_to: 198 }));
     }
-}
 
-impl AbstractedRaise {
-    fn allowed_amounts(&self) -> &Vec<i32> {
-        &self.amounts
+    #[test]
+    fn incomplete_all_in_does_not_reopen_betting() {
+        // Seat 2 raises by 8 and the small blind shoves for a raise of only 5.
+        let state = play(dealt_hand(&[15, 200, 200]), &[Action::RaiseTo(10), Action::AllIn]);
+        assert_eq!(state.table().bets[0], 15);
+        // The big blind has not acted yet, so may still raise, by at least 8.
+        assert_eq!(state.player_i(), 1);
+        assert_eq!(legal_actions(&state).raise, Some(RaiseRange { min_to: 23, max_to: 200 }));
+        let state = state.apply_action(&Action::Call);
+        // The original raiser may only call or fold.
+        assert_eq!(state.player_i(), 2);
+        assert_eq!(
+            legal_actions(&state),
+            LegalActions {
+                fold: true,
+                check: false,
+                call: Some(5),
+                raise: None,
+            }
+        );
+        assert!(!state.is_legal(&Action::RaiseTo(30)));
     }
 }
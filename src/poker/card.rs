@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use super::deck::Deck;
 use super::evaluation::eval_card::{
     EvaluationCard
 };
@@ -26,10 +27,10 @@ impl Card {
             "8" => 8,
             "9" => 9,
             "10" | "t" => 10,
-            "j" => 11,
-            "q" => 12,
-            "k" => 13,
-            "a" => 14,
+            "11" | "j" => 11,
+            "12" | "q" => 12,
+            "13" | "k" => 13,
+            "14" | "a" => 14,
             _ => panic!("Invalid rank"),
         };
 
@@ -109,7 +110,31 @@ impl Card {
         self.eval_card
     }
 
-    fn rank_int(&self) -> i32 {
+    pub fn to_u8(&self) -> u8 {
+        // Rank-major: 4 * (rank - 2) plus the suit's position in the full
+        // deck, so a card fits in a byte and a set of cards in a u64.
+        let suit_i = Deck::DEFAULT_INCLUDE_SUITS
+            .iter()
+            .position(|&suit| suit == self.suit)
+            .unwrap();
+        (4 * (self.rank - 2)) as u8 + suit_i as u8
+    }
+
+    pub fn from_u8(card: u8) -> Card {
+        let rank = (card / 4 + 2) as i32;
+        Card::new(&rank.to_string(), Deck::DEFAULT_INCLUDE_SUITS[(card % 4) as usize])
+    }
+
+    pub fn to_mask(cards: &[Card]) -> u64 {
+        // A set of cards as bits at their `to_u8` positions.
+        cards.iter().fold(0, |mask, card| mask | 1 << card.to_u8())
+    }
+
+    pub fn from_mask(mask: u64) -> Vec<Card> {
+        (0..64).filter(|&card| mask & 1 << card != 0).map(Card::from_u8).collect()
+    }
+
+    pub fn rank_int(&self) -> i32 {
         self.rank
     }
 
@@ -117,7 +142,7 @@ impl Card {
         Self::rank_to_str(self.rank)
     }
 
-    fn suit(&self) -> &str {
+    pub fn suit(&self) -> &str {
         &self.suit
     }
 }
@@ -6,7 +6,7 @@ use crate::poker::card::Card;
 use std::vec::Vec;
 
 
-
+#[derive(Clone)]
 pub struct Deck {
     include_suits: Vec<&'static str>,
     include_ranks: Vec<i32>,
@@ -19,35 +19,41 @@ impl Deck  {
     pub const DEFAULT_INCLUDE_RANKS: [i32; 13] = [2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14];
 
     pub fn new(include_suits_kw:Option<Vec<&'static str>>, include_ranks_kw:Option<Vec<i32>>) -> Deck {
-        let suits = Deck::DEFAULT_INCLUDE_SUITS.to_vec();
-        let ranks = Deck::DEFAULT_INCLUDE_RANKS.to_vec();
-        let mut cards_in_deck = Vec::new();
-        for suit in suits.iter() {
-            for rank in ranks.iter() {
-                cards_in_deck.push(Card::new(&rank.to_string(), suit));
-            }
-        }
-        let mut rng = thread_rng();
-        cards_in_deck.shuffle(&mut rng);
-        Deck {
-            include_suits: include_suits_kw.unwrap_or(suits),
-            include_ranks: include_ranks_kw.unwrap_or(ranks),
-            cards_in_deck,
+        let mut deck = Deck {
+            include_suits: include_suits_kw.unwrap_or(Deck::DEFAULT_INCLUDE_SUITS.to_vec()),
+            include_ranks: include_ranks_kw.unwrap_or(Deck::DEFAULT_INCLUDE_RANKS.to_vec()),
+            cards_in_deck: Vec::new(),
             dealt_cards: Vec::new(),
-        }
+        };
+        deck.reset();
+        deck
     }
 
-    pub fn len(&self) -> usize {
-        self.cards_in_deck.len() + self.dealt_cards.len()
+    pub fn include_suits(&self) -> &[&'static str] {
+        &self.include_suits
     }
 
-    pub fn reset(&mut self) {
-        self.cards_in_deck = Vec::new();
+    pub fn include_ranks(&self) -> &[i32] {
+        &self.include_ranks
+    }
+
+    pub fn all_cards(&self) -> Vec<Card> {
+        // Every card this deck can deal, in a fixed suit-major order.
+        let mut cards = Vec::new();
         for suit in self.include_suits.iter() {
             for rank in self.include_ranks.iter() {
-                self.cards_in_deck.push(Card::new(&rank.to_string(), suit));
+                cards.push(Card::new(&rank.to_string(), suit));
             }
         }
+        cards
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards_in_deck.len() + self.dealt_cards.len()
+    }
+
+    pub fn reset(&mut self) {
+        self.cards_in_deck = self.all_cards();
         let mut rng = thread_rng();
         self.cards_in_deck.shuffle(&mut rng);
         self.dealt_cards = Vec::new();
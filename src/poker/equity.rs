@@ -0,0 +1,345 @@
+/// Showdown equity for two or more players, from known hole cards or ranges
+/// of weighted combos, on an empty or partial board with dead cards removed.
+/// Equity is either exact, enumerating every deal of the combos and every
+/// way to complete the board, or a Monte Carlo estimate from random deals.
+///
+/// Cards are handled as `Card::to_mask` sets internally, and runouts are
+/// walked over those sets, so deals and showdowns do not allocate. A player
+/// who ties with k - 1 others for the best hand gets 1/k of the pot, which
+/// counts towards their equity.
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::ThreadRng;
+use rand::{thread_rng, Rng};
+
+use super::card::Card;
+use super::evaluation::evaluator::Evaluator;
+
+const FULL_DECK: u64 = (1 << 52) - 1;
+const NO_DEAL: &str = "The ranges leave no way to deal every player a hand";
+
+/// What is known about one player's hole cards. Combos in a range that share
+/// a card with the board, the dead cards or a known hand are left out, and
+/// weights need not sum to one.
+#[derive(Clone, Debug)]
+pub enum HoleCards {
+    Known([Card; 2]),
+    Range(Vec<([Card; 2], f64)>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    Exact,
+    MonteCarlo { n_samples: usize },
+}
+
+/// One player's share of the outcomes: how often they win alone, tie for the
+/// best hand and lose, and the share of the pot they expect, which is wins
+/// plus their part of ties.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Equity {
+    pub win: f64,
+    pub tie: f64,
+    pub lose: f64,
+    pub equity: f64,
+}
+
+/// Equities in player order, and the number of deals they were computed
+/// from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EquityResult {
+    pub players: Vec<Equity>,
+    pub n_deals: usize,
+}
+
+pub fn equity(players: &[HoleCards], board: &[Card], dead_cards: &[Card], method: Method) -> EquityResult {
+    if players.len() < 2 {
+        panic!("Equity needs at least two players, not {}", players.len());
+    }
+    if board.len() > 5 {
+        panic!("A board has at most 5 cards, not {}", board.len());
+    }
+    let board_mask = Card::to_mask(board);
+    let dead_mask = Card::to_mask(dead_cards);
+    if board_mask & dead_mask != 0 || board_mask.count_ones() as usize != board.len() {
+        panic!("The board and dead cards must be distinct cards");
+    }
+
+    // Known hands block cards for every other player, like dead cards.
+    let mut blocked = board_mask | dead_mask;
+    for hole_cards in players {
+        if let HoleCards::Known(cards) = hole_cards {
+            let hand = Card::to_mask(cards);
+            if hand & blocked != 0 || hand.count_ones() != 2 {
+                panic!("Known hands can not share cards with each other or the board");
+            }
+            blocked |= hand;
+        }
+    }
+    let ranges: Vec<Vec<(u64, f64)>> = players
+        .iter()
+        .enumerate()
+        .map(|(player_i, hole_cards)| {
+            let combos: Vec<(u64, f64)> = match hole_cards {
+                HoleCards::Known(cards) => vec![(Card::to_mask(cards), 1.0)],
+                HoleCards::Range(combos) => combos
+                    .iter()
+                    .map(|(cards, weight)| (Card::to_mask(cards), *weight))
+                    .filter(|&(hand, weight)| hand & blocked == 0 && weight > 0.0)
+                    .collect(),
+            };
+            if combos.is_empty() {
+                panic!("Player {}'s range has no hands left after removing known cards", player_i);
+            }
+            combos
+        })
+        .collect();
+
+    let mut showdown = Showdown::new(players.len(), 5 - board.len());
+    match method {
+        Method::Exact => showdown.enumerate(&ranges, board_mask, board_mask | dead_mask),
+        Method::MonteCarlo { n_samples: 0 } => panic!("Monte Carlo equity needs at least one sample"),
+        Method::MonteCarlo { n_samples } => showdown.sample(&ranges, board_mask, board_mask | dead_mask, n_samples),
+    }
+    // Sampling gives up on its own, after many deals that collide.
+    if showdown.n_deals == 0 {
+        panic!("{}", NO_DEAL);
+    }
+    showdown.result()
+}
+
+/// Running totals over deals, weighted by how likely each deal is.
+struct Showdown {
+    evaluator: Evaluator,
+    n_cards_to_come: usize,
+    hands: Vec<u64>,
+    ranks: Vec<i32>,
+    wins: Vec<f64>,
+    ties: Vec<f64>,
+    equities: Vec<f64>,
+    total_weight: f64,
+    n_deals: usize,
+}
+
+impl Showdown {
+    fn new(n_players: usize, n_cards_to_come: usize) -> Showdown {
+        Showdown {
+            evaluator: Evaluator::new(),
+            n_cards_to_come,
+            hands: vec![0; n_players],
+            ranks: vec![0; n_players],
+            wins: vec![0.0; n_players],
+            ties: vec![0.0; n_players],
+            equities: vec![0.0; n_players],
+            total_weight: 0.0,
+            n_deals: 0,
+        }
+    }
+
+    fn enumerate(&mut self, ranges: &[Vec<(u64, f64)>], board: u64, used: u64) {
+        self.enumerate_hands(ranges, board, used, 1.0, 0);
+    }
+
+    fn enumerate_hands(&mut self, ranges: &[Vec<(u64, f64)>], board: u64, used: u64, weight: f64, player_i: usize) {
+        // Every combination of compatible combos, then every runout of the
+        // cards left.
+        if player_i == ranges.len() {
+            self.enumerate_runouts(!used & FULL_DECK, board, weight, self.n_cards_to_come);
+            return;
+        }
+        for &(hand, hand_weight) in &ranges[player_i] {
+            if hand & used == 0 {
+                self.hands[player_i] = hand;
+                self.enumerate_hands(ranges, board, used | hand, weight * hand_weight, player_i + 1);
+            }
+        }
+    }
+
+    fn enumerate_runouts(&mut self, deck: u64, board: u64, weight: f64, n_cards: usize) {
+        // Each card of the deck in turn, then runouts of the cards above it,
+        // so every set of `n_cards` comes up once.
+        if n_cards == 0 {
+            self.add_deal(board, weight);
+            return;
+        }
+        let mut deck = deck;
+        while deck != 0 {
+            let card = deck & deck.wrapping_neg();
+            deck &= deck - 1;
+            self.enumerate_runouts(deck, board | card, weight, n_cards - 1);
+        }
+    }
+
+    fn sample(&mut self, ranges: &[Vec<(u64, f64)>], board: u64, used: u64, n_samples: usize) {
+        // Each player's combo is drawn by weight and the whole deal is drawn
+        // again if two of them collide, so deals come up in proportion to
+        // the product of their weights.
+        const MAX_ATTEMPTS: usize = 100_000;
+        let distributions: Vec<WeightedIndex<f64>> = ranges
+            .iter()
+            .map(|combos| WeightedIndex::new(combos.iter().map(|&(_, weight)| weight)).unwrap())
+            .collect();
+        let mut rng = thread_rng();
+        for _ in 0..n_samples {
+            let mut n_attempts = 0;
+            let mut deck = !used & FULL_DECK;
+            while !self.sample_hands(ranges, &distributions, &mut deck, &mut rng) {
+                n_attempts += 1;
+                if n_attempts == MAX_ATTEMPTS {
+                    panic!("{}", NO_DEAL);
+                }
+                deck = !used & FULL_DECK;
+            }
+            let mut full_board = board;
+            for _ in 0..self.n_cards_to_come {
+                let card = random_card(deck, &mut rng);
+                deck &= !(1 << card);
+                full_board |= 1 << card;
+            }
+            self.add_deal(full_board, 1.0);
+        }
+    }
+
+    fn sample_hands(
+        &mut self,
+        ranges: &[Vec<(u64, f64)>],
+        distributions: &[WeightedIndex<f64>],
+        deck: &mut u64,
+        rng: &mut ThreadRng,
+    ) -> bool {
+        for (player_i, distribution) in distributions.iter().enumerate() {
+            let hand = ranges[player_i][distribution.sample(rng)].0;
+            if hand & !*deck != 0 {
+                return false;
+            }
+            self.hands[player_i] = hand;
+            *deck &= !hand;
+        }
+        true
+    }
+
+    fn add_deal(&mut self, board: u64, weight: f64) {
+        for (rank, &hand) in self.ranks.iter_mut().zip(&self.hands) {
+            *rank = self.evaluator.evaluate_mask(board | hand);
+        }
+        // Lower ranks are better hands.
+        let best_rank = *self.ranks.iter().min().unwrap();
+        let n_winners = self.ranks.iter().filter(|&&rank| rank == best_rank).count();
+        for (player_i, &rank) in self.ranks.iter().enumerate() {
+            if rank == best_rank {
+                if n_winners == 1 {
+                    self.wins[player_i] += weight;
+                } else {
+                    self.ties[player_i] += weight;
+                }
+                self.equities[player_i] += weight / n_winners as f64;
+            }
+        }
+        self.total_weight += weight;
+        self.n_deals += 1;
+    }
+
+    fn result(&self) -> EquityResult {
+        let players = (0..self.hands.len())
+            .map(|player_i| {
+                let win = self.wins[player_i] / self.total_weight;
+                let tie = self.ties[player_i] / self.total_weight;
+                Equity {
+                    win,
+                    tie,
+                    lose: 1.0 - win - tie,
+                    equity: self.equities[player_i] / self.total_weight,
+                }
+            })
+            .collect();
+        EquityResult {
+            players,
+            n_deals: self.n_deals,
+        }
+    }
+}
+
+fn random_card(deck: u64, rng: &mut ThreadRng) -> u8 {
+    // A uniformly random card of the set, by skipping a random number of its
+    // lowest cards.
+    let mut deck = deck;
+    for _ in 0..rng.gen_range(0..deck.count_ones()) {
+        deck &= deck - 1;
+    }
+    deck.trailing_zeros() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(cards: &[(&str, &str)]) -> Vec<Card> {
+        cards.iter().map(|&(rank, suit)| Card::new(rank, suit)).collect()
+    }
+
+    fn known(hand: &[(&str, &str)]) -> HoleCards {
+        let hand = cards(hand);
+        HoleCards::Known([hand[0].clone(), hand[1].clone()])
+    }
+
+    fn aces_and_kings() -> [HoleCards; 2] {
+        [known(&[("A", "spades"), ("A", "hearts")]), known(&[("K", "spades"), ("K", "hearts")])]
+    }
+
+    fn flop() -> Vec<Card> {
+        cards(&[("2", "clubs"), ("7", "diamonds"), ("9", "hearts")])
+    }
+
+    #[test]
+    fn exact_aces_against_kings_on_a_flop() {
+        // Kings win with one of the two kings left, or a straight, of the
+        // 45 * 44 / 2 turns and rivers.
+        let result = equity(&aces_and_kings(), &flop(), &[], Method::Exact);
+        assert_eq!(result.n_deals, 990);
+        assert!((result.players[0].equity - 907.0 / 990.0).abs() < 1e-12);
+        assert!((result.players[1].equity - 83.0 / 990.0).abs() < 1e-12);
+        assert_eq!(result.players[0].tie, 0.0);
+    }
+
+    #[test]
+    fn monte_carlo_approaches_exact() {
+        let result = equity(&aces_and_kings(), &flop(), &[], Method::MonteCarlo { n_samples: 20_000 });
+        assert_eq!(result.n_deals, 20_000);
+        assert!((result.players[0].equity - 907.0 / 990.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn identical_hands_split_the_pot() {
+        let players = [known(&[("A", "spades"), ("K", "diamonds")]), known(&[("A", "clubs"), ("K", "hearts")])];
+        let board = cards(&[("2", "spades"), ("3", "diamonds"), ("8", "clubs"), ("9", "hearts"), ("J", "spades")]);
+        let result = equity(&players, &board, &[], Method::Exact);
+        assert_eq!(result.n_deals, 1);
+        assert_eq!(result.players[0], Equity { win: 0.0, tie: 1.0, lose: 0.0, equity: 0.5 });
+    }
+
+    #[test]
+    #[should_panic(expected = "no way to deal")]
+    fn exact_without_a_deal() {
+        // Both ranges need the ace of spades.
+        let players = [
+            HoleCards::Range(vec![([Card::new("A", "spades"), Card::new("A", "hearts")], 1.0)]),
+            HoleCards::Range(vec![([Card::new("A", "spades"), Card::new("A", "diamonds")], 1.0)]),
+        ];
+        equity(&players, &flop(), &[], Method::Exact);
+    }
+
+    #[test]
+    #[should_panic(expected = "no way to deal")]
+    fn monte_carlo_without_a_deal() {
+        let players = [
+            HoleCards::Range(vec![([Card::new("A", "spades"), Card::new("A", "hearts")], 1.0)]),
+            HoleCards::Range(vec![([Card::new("A", "spades"), Card::new("A", "diamonds")], 1.0)]),
+        ];
+        equity(&players, &flop(), &[], Method::MonteCarlo { n_samples: 10 });
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one sample")]
+    fn monte_carlo_without_samples() {
+        equity(&aces_and_kings(), &flop(), &[], Method::MonteCarlo { n_samples: 0 });
+    }
+}
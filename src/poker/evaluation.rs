@@ -1,3 +1,4 @@
 pub mod eval_card;
 pub mod lookup;
-pub mod evaluator;
\ No newline at end of file
+pub mod evaluator;
+pub mod seven_card;
\ No newline at end of file
@@ -173,38 +173,4 @@ impl EvaluationCard{
             
         bhand
     }
-
-    pub fn prime_product_from_hand(card_ints: Vec<i32>) -> i32 {
-        let mut product = 1;
-        for c in card_ints {
-            product *= c & 0xFF;
-        }
-        product
-    }
-
-    pub fn prime_product_from_rankbits(rankbits: i32) -> i32 {
-        // Returns the prime product using the bitrank (b)
-        // bits of the hand. Each 1 in the sequence is converted
-        // to the correct prime and multiplied in.
-        // Params:
-        //     rankbits = a single 32-bit (only 13-bits set) integer representing
-        //             the ranks of 5 _different_ ranked cards
-        //             (5 of 13 bits are set)
-        // Primarily used for evaulating flushes and straights,
-        // two occasions where we know the ranks are *ALL* different.
-        // Assumes that the input is in form (set bits):
-        //                         rankbits
-        //                 +--------+--------+
-        //                 |xxxbbbbb|bbbbbbbb|
-        //                 +--------+--------+
-
-        let mut product = 1;
-        for i in EvaluationCard::INT_RANKS{
-            if (rankbits & (1 << i)) != 0{
-                product *= EvaluationCard::PRIMES[i as usize];
-            }
-        }
-        product
-    }
 }
-
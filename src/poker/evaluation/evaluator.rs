@@ -6,81 +6,93 @@
 /// consequent evaluations are very fast. Won't beat C, but very fast as
 /// all calculations are done with bit arithmetic and table lookups.
 
-use itertools::Itertools;
-
 use super::super::card::Card;
 
 use super::lookup::{
     LookupTable,
-    MaxHand
+    MaxHand,
+    LOOKUP_TABLE
 };
-use super::eval_card::EvaluationCard;
+use super::seven_card::{PartialHand, SevenCardTable};
 
 pub struct Evaluator{
-    table: LookupTable,
+    table: &'static LookupTable,
+    seven_card_table: &'static SevenCardTable,
 }
 
 impl Evaluator {
     pub fn new() -> Evaluator{
+        // The tables are shared, so evaluators are free to create.
         Evaluator {
-            table: LookupTable::new(),
+            table: &LOOKUP_TABLE,
+            seven_card_table: SevenCardTable::shared(),
         }
     }
 
     pub fn evaluate(&self, cards:&Vec<Card>, board:&Vec<Card>) -> i32{
-        let all_cards: Vec<i32> = cards.iter()
-            .chain(board.iter())
-            .map(|card| card.clone().into())
-            .collect();
-
-        match all_cards.len() {
-            5 => self._five(all_cards),
-            6 => self._six(all_cards),
-            7 => self._seven(all_cards),
-            _ => panic!("number of cards on table is not 5, 6 or 7")
+        // Cards are gathered on the stack, so nothing is allocated.
+        let n_cards = cards.len() + board.len();
+        let mut all_cards = [0; 7];
+        if !(5..=7).contains(&n_cards) {
+            panic!("number of cards on table is not 5, 6 or 7")
+        }
+        for (slot, card) in all_cards.iter_mut().zip(cards.iter().chain(board.iter())) {
+            *slot = card.eval_card;
         }
-    }
 
-    fn _five(&self, cards:Vec<i32>) -> i32 {
-        // if flush
-        if cards[0] & cards[1] & cards[2] & cards[3] & cards[4] & 0xF000 != 0 {
-            let hand_or = (cards[0] | cards[1] | cards[2] | cards[3] | cards[4]) >> 16;
-            let prime = EvaluationCard::prime_product_from_rankbits(hand_or);
-            *self.table.flush_lookup.get(&prime).unwrap()
-        } else {
-            let prime = EvaluationCard::prime_product_from_hand(cards);
-            *self.table.unsuited_lookup.get(&prime).unwrap()
+        match n_cards {
+            5 => self._five(&all_cards[..5]),
+            _ => self.seven_card_table.evaluate(&PartialHand::from_eval_cards(&all_cards[..n_cards])),
         }
     }
 
-    fn _six(&self, cards:Vec<i32>) -> i32 {
-        let mut minimum = LookupTable::MAX_HIGH_CARD;
+    pub fn evaluate_mask(&self, cards: u64) -> i32 {
+        // Five to seven cards as a `Card::to_mask` set.
+        self.seven_card_table.evaluate(&PartialHand::from_mask(cards))
+    }
 
-        for combo in cards.iter().combinations(5){
-            let combo = combo.iter().map(|&x| *x).collect();
-            let score = self._five(combo);
-            if score < minimum {
-                minimum = score;
-            }
-        } 
+    pub fn evaluate_batch(&self, board: &[Card], hands: &[[Card; 2]]) -> Vec<i32> {
+        // The board is gathered once, then each hand only adds its own two
+        // cards to a copy of it.
+        let board = PartialHand::from_mask(Card::to_mask(board));
+        hands
+            .iter()
+            .map(|hand| {
+                let mut cards = board;
+                cards.add(hand[0].to_u8());
+                cards.add(hand[1].to_u8());
+                self.seven_card_table.evaluate(&cards)
+            })
+            .collect()
+    }
 
-        minimum
+    pub fn evaluate_masks(&self, board: u64, hands: &[u64]) -> Vec<i32> {
+        // As `evaluate_batch`, with the board and hands as card masks.
+        let board = PartialHand::from_mask(board);
+        hands
+            .iter()
+            .map(|&hand| {
+                let mut cards = board;
+                cards.add_mask(hand);
+                self.seven_card_table.evaluate(&cards)
+            })
+            .collect()
     }
-            
-    fn _seven(&self, cards:Vec<i32>) -> i32 {
-        let mut minimum = LookupTable::MAX_HIGH_CARD;
-
-        for combo in cards.iter().combinations(5){
-            let combo = combo.iter().map(|&x| *x).collect();
-            let score = self._five(combo);
-            if score < minimum {
-                minimum = score;
-            }
-        } 
 
-        minimum
+    fn _five(&self, cards:&[i32]) -> i32 {
+        // if flush
+        if cards[0] & cards[1] & cards[2] & cards[3] & cards[4] & 0xF000 != 0 {
+            let hand_or = (cards[0] | cards[1] | cards[2] | cards[3] | cards[4]) >> 16;
+            self.table.flush_rank(hand_or)
+        } else {
+            let mut counts = [0; 13];
+            for card in cards {
+                counts[((card >> 8) & 0xF) as usize] += 1;
+            }
+            self.table.unsuited_rank(&counts)
+        }
     }
-        
+
     pub fn get_rank_class(&self, hr:i32) -> i32 {
         // Returns the class of hand from the hand hand_rank from evaluate.
         if (0..=LookupTable::MAX_STRAIGHT_FLUSH).contains(&hr){
@@ -187,4 +199,83 @@ impl Evaluator {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn five_card_hands() {
+        // Every five card hand, counted by class from straight flush down to
+        // high card, against the well known totals.
+        let evaluator = Evaluator::new();
+        let deck: Vec<Card> = (0..52).map(Card::from_u8).collect();
+        let mut class_counts = [0; 9];
+        let mut ranks = HashSet::new();
+        for hand in deck.iter().cloned().combinations(5) {
+            let rank = evaluator.evaluate(&hand[..2].to_vec(), &hand[2..].to_vec());
+            class_counts[evaluator.get_rank_class(rank) as usize - 1] += 1;
+            ranks.insert(rank);
+        }
+        assert_eq!(class_counts, [40, 624, 3744, 5108, 10200, 54912, 123552, 1098240, 1302540]);
+        assert_eq!(ranks.len(), 7462);
+    }
+
+    fn class_counts(n_cards: usize) -> [usize; 9] {
+        // Every hand of `n_cards` by its best five cards' class, walking the
+        // card masks directly to keep the millions of hands quick.
+        fn count(evaluator: &Evaluator, cards: u64, first_card: u32, n_left: usize, counts: &mut [usize; 9]) {
+            if n_left == 0 {
+                counts[evaluator.get_rank_class(evaluator.evaluate_mask(cards)) as usize - 1] += 1;
+                return;
+            }
+            for card in first_card..=52 - n_left as u32 {
+                count(evaluator, cards | 1 << card, card + 1, n_left - 1, counts);
+            }
+        }
+        let mut counts = [0; 9];
+        count(&Evaluator::new(), 0, 0, n_cards, &mut counts);
+        counts
+    }
+
+    #[test]
+    fn six_card_hands() {
+        assert_eq!(class_counts(6), [1844, 14664, 165984, 205792, 361620, 732160, 2532816, 9730740, 6612900]);
+    }
+
+    #[test]
+    #[ignore = "evaluates all 133,784,560 seven card hands"]
+    fn seven_card_hands() {
+        assert_eq!(
+            class_counts(7),
+            [41584, 224848, 3473184, 4047644, 6180020, 6461620, 31433400, 58627800, 23294460]
+        );
+    }
+
+    #[test]
+    fn batch_matches_single_hands() {
+        // Every hand that fits with a flop, a turn and a river.
+        let evaluator = Evaluator::new();
+        let deck: Vec<Card> = (0..52).map(Card::from_u8).collect();
+        let boards = [[0, 17, 42].as_slice(), &[3, 8, 30, 51], &[12, 13, 14, 15, 40]];
+        for board in boards {
+            let board: Vec<Card> = board.iter().map(|&card| Card::from_u8(card)).collect();
+            let board_mask = Card::to_mask(&board);
+            let hands: Vec<[Card; 2]> = deck
+                .iter()
+                .filter(|card| board_mask & 1 << card.to_u8() == 0)
+                .cloned()
+                .tuple_combinations()
+                .map(|(card_a, card_b)| [card_a, card_b])
+                .collect();
+            let hand_masks: Vec<u64> = hands.iter().map(|hand| Card::to_mask(hand)).collect();
+            let expected: Vec<i32> = hands.iter().map(|hand| evaluator.evaluate(&hand.to_vec(), &board)).collect();
+            assert_eq!(evaluator.evaluate_batch(&board, &hands), expected);
+            assert_eq!(evaluator.evaluate_masks(board_mask, &hand_masks), expected);
+        }
+    }
+}
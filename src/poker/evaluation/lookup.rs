@@ -10,20 +10,18 @@
 /// High Card      + 1277     [(13 choose 5) - 10 straights]
 /// -------------------------
 /// TOTAL            7462
-/// Here we create a lookup table which maps:
-///     5 card hand's unique prime product => rank in range [1, 7462]
+/// Here we create two lookup tables which map a 5 card hand to its rank in
+/// range [1, 7462]:
+/// * Flushes, by the 13 rankbits of the hand (2^13 > 7462)
+/// * Everything else, by `rank_index` of the hand's rank counts, a minimal
+///   perfect hash of the hand's unique prime product
+///
 /// Examples:
 /// * Royal flush (best hand possible)          => 1
 /// * 7-5-4-3-2 unsuited (worst hand possible)  => 7462
-use std::collections::HashMap;
-use super::eval_card::{
-    EvaluationCard
-};
-use std::fs::File;
-use std::io::{BufWriter, Write};
-use itertools::Itertools;
-
-
+///
+/// The tables are built by const functions, so they are part of the binary
+/// and cost nothing at runtime.
 pub enum MaxHand {
     StraightFlush,
     FourOfAKind,
@@ -36,8 +34,67 @@ pub enum MaxHand {
     HighCard,
 }
 pub struct LookupTable {
-    pub flush_lookup: HashMap<i32, i32>,
-    pub unsuited_lookup: HashMap<i32, i32>,
+    pub flush_lookup: [u16; 1 << 13],
+    pub unsuited_lookup: [u16; N_UNSUITED_HANDS],
+}
+
+pub static LOOKUP_TABLE: LookupTable = LookupTable::new();
+
+// Straights in rank order, as rankbits.
+const STRAIGHTS: [usize; 10] = [
+    7936,  // int('0b1111100000000', 2), // royal flush
+    3968,  // int('0b111110000000', 2),
+    1984,  // int('0b11111000000', 2),
+    992,  // int('0b1111100000', 2),
+    496,  // int('0b111110000', 2),
+    248,  // int('0b11111000', 2),
+    124,  // int('0b1111100', 2),
+    62,  // int('0b111110', 2),
+    31,  // int('0b11111', 2),
+    4111,  // int('0b1000000001111', 2) // 5 high
+];
+
+// The number of ways to hold k cards in the last n ranks, with at most four of
+// any rank, for n up to 13 and k up to 7.
+pub const N_RANK_MULTISETS: [[usize; 8]; 14] = count_rank_multisets();
+pub const N_UNSUITED_HANDS: usize = N_RANK_MULTISETS[13][5];
+
+const fn count_rank_multisets() -> [[usize; 8]; 14] {
+    let mut counts = [[0; 8]; 14];
+    counts[0][0] = 1;
+    let mut n = 1;
+    while n < 14 {
+        let mut k = 0;
+        while k < 8 {
+            let mut n_of_rank = 0;
+            while n_of_rank <= 4 && n_of_rank <= k {
+                counts[n][k] += counts[n - 1][k - n_of_rank];
+                n_of_rank += 1;
+            }
+            k += 1;
+        }
+        n += 1;
+    }
+    counts
+}
+
+pub const fn rank_index(counts: &[u8; 13], n_cards: usize) -> usize {
+    // The position of a multiset of ranks in lexicographic order of its rank
+    // counts. Every multiset with a smaller count at the first rank where they
+    // differ comes first, counted by the ranks and cards left after it.
+    let mut index = 0;
+    let mut n_cards_left = n_cards;
+    let mut r = 0;
+    while r < 13 {
+        let mut smaller_count = 0;
+        while smaller_count < counts[r] as usize {
+            index += N_RANK_MULTISETS[12 - r][n_cards_left - smaller_count];
+            smaller_count += 1;
+        }
+        n_cards_left -= counts[r] as usize;
+        r += 1;
+    }
+    index
 }
 
 impl LookupTable {
@@ -81,120 +138,99 @@ impl LookupTable {
         }
     }
     
-    pub fn new() -> LookupTable {
+    pub const fn new() -> LookupTable {
         let mut lookup_table:LookupTable = LookupTable {
-            flush_lookup: HashMap::new(),
-            unsuited_lookup: HashMap::new(),
+            flush_lookup: [0; 1 << 13],
+            unsuited_lookup: [0; N_UNSUITED_HANDS],
         };
         lookup_table.flushes();
         lookup_table.multiples();
         lookup_table
     }
 
-    fn flushes(&mut self){
+    pub fn flush_rank(&self, rankbits: i32) -> i32 {
+        self.flush_lookup[rankbits as usize] as i32
+    }
+
+    pub fn unsuited_rank(&self, counts: &[u8; 13]) -> i32 {
+        self.unsuited_lookup[rank_index(counts, 5)] as i32
+    }
+
+    const fn flushes(&mut self){
         // Straight flushes and flushes.
 
         // Lookup is done on 13 bit integer (2^13 > 7462):
         // xxxbbbbb bbbbbbbb => integer hand index
-        
-
-        // straight flushes in rank order
-        let straight_flushes: Vec<i32> = Vec::from([
-            7936,  // int('0b1111100000000', 2), // royal flush
-            3968,  // int('0b111110000000', 2),
-            1984,  // int('0b11111000000', 2),
-            992,  // int('0b1111100000', 2),
-            496,  // int('0b111110000', 2),
-            248,  // int('0b11111000', 2),
-            124,  // int('0b1111100', 2),
-            62,  // int('0b111110', 2),
-            31,  // int('0b11111', 2),
-            4111,  // int('0b1000000001111', 2) // 5 high
-        ]);
-
-        // now we'll dynamically generate all the other
-        // flushes (including straight flushes)
-        let mut flushes = Vec::new();
-        let intval = i32::from_str_radix("0b11111", 2).unwrap();
-        let mut gen = LookupTable::get_lexographically_next_bit_sequence(intval);
-
-        // 1277 = number of high cards
-        // 1277 + len(str_flushes) is number of hands with all cards unique rank
-        let flush_len = 0..(1277 + straight_flushes.len() - 1);
-        for _ in flush_len {
-            // we also iterate over SFs
-            // pull the next flush pattern from our generator
-            let f = gen.next().unwrap();
-            
-            // if this flush matches perfectly any
-            // straight flush, do not add it
-            let mut not_sf: bool = true;
-            for sf in &straight_flushes {
-                // if f XOR sf == 0, then bit pattern
-                // is same, and we should not add
-                if (f ^ sf) == 0 {
-                    not_sf = false
-                };
-            }
-                
 
-            if not_sf {
-                flushes.push(f)
-            };
-        }
-        // we started from the lowest straight pattern, now we want to start
-        // ranking from the most powerful hands, so we reverse
-        flushes.reverse();
-        // now add to the lookup map:
         // start with straight flushes and the rank of 1
         // since it is the best hand in poker
         // rank 1 = Royal Flush!
-        LookupTable::fill_in_lookup_table(
-            1,
-            &straight_flushes,
-            &mut self.flush_lookup
-        );
-        // we start the counting for flushes on max full house, which
+        let mut i = 0;
+        while i < STRAIGHTS.len() {
+            self.flush_lookup[STRAIGHTS[i]] = (1 + i) as u16;
+            i += 1;
+        }
+
+        // now we'll go through all the other flushes, from the highest
+        // down. We start the counting for flushes on max full house, which
         // is the worst rank that a full house can have (2,2,2,3,3)
-        LookupTable::fill_in_lookup_table(
-            LookupTable::MAX_FULL_HOUSE + 1,
-            &flushes,
-            &mut self.flush_lookup
-        );
+        let mut rank = LookupTable::MAX_FULL_HOUSE + 1;
+        let mut rankbits: usize = 1 << 13;
+        while rankbits > 0 {
+            rankbits -= 1;
+            if rankbits.count_ones() == 5 && !LookupTable::is_straight(rankbits) {
+                self.flush_lookup[rankbits] = rank as u16;
+                rank += 1;
+            }
+        }
+
         // we can reuse these bit sequences for straights
         // and high cards since they are inherently related
         // and differ only by context
-        self.straight_and_highcards(&straight_flushes, &flushes);
-            
+        self.straight_and_highcards();
     }
 
-    fn fill_in_lookup_table(rank_init: i32, rankbits_list: &Vec<i32>, lookup_table: &mut HashMap<i32, i32>) {
-        let mut rank = rank_init;
-        for rb in rankbits_list{
-            let prime_product = EvaluationCard::prime_product_from_rankbits(*rb);
-            lookup_table.insert(prime_product, rank);
-            rank += 1;
+    const fn is_straight(rankbits: usize) -> bool {
+        let mut i = 0;
+        while i < STRAIGHTS.len() {
+            if rankbits == STRAIGHTS[i] {
+                return true;
+            }
+            i += 1;
         }
+        false
     }
 
-    fn multiples(&mut self) {
-        let backwards_ranks = (0..EvaluationCard::INT_RANKS.len())
-            .rev()
-            .collect::<Vec<usize>>();
+    const fn insert(&mut self, ranks: [usize; 5], rank: i32) {
+        // Adds an unsuited hand, given the rank of each of its cards.
+        let mut counts = [0; 13];
+        let mut i = 0;
+        while i < 5 {
+            counts[ranks[i]] += 1;
+            i += 1;
+        }
+        self.unsuited_lookup[rank_index(&counts, 5)] = rank as u16;
+    }
+
+    const fn multiples(&mut self) {
+        // Ranks are visited from ace down to deuce, so better hands come
+        // first within each category.
 
         // 1) Four of a Kind
         let mut rank = LookupTable::MAX_STRAIGHT_FLUSH + 1;
 
         // for each choice of a set of four rank
-        for i in &backwards_ranks {
+        let mut i = 13;
+        while i > 0 {
+            i -= 1;
             // and for each possible kicker rank
-            let mut kickers = backwards_ranks.clone();
-            kickers.remove(*i);
-            for k in &kickers {
-                let product =
-                    EvaluationCard::PRIMES[*i].pow(4) * EvaluationCard::PRIMES[*k];
-                self.unsuited_lookup.insert(product, rank);
-                rank += 1;
+            let mut k = 13;
+            while k > 0 {
+                k -= 1;
+                if k != i {
+                    self.insert([i, i, i, i, k], rank);
+                    rank += 1;
+                }
             }
         }
 
@@ -202,15 +238,17 @@ impl LookupTable {
         rank = LookupTable::MAX_FOUR_OF_A_KIND + 1;
 
         // for each three of a kind
-        for i in &backwards_ranks {
+        let mut i = 13;
+        while i > 0 {
+            i -= 1;
             // and for each choice of pair rank
-            let mut pairranks = backwards_ranks.clone();
-            pairranks.remove(*i);
-            for pr in &pairranks {
-                let product = EvaluationCard::PRIMES[*i].pow(3)
-                    * EvaluationCard::PRIMES[*pr].pow(2);
-                self.unsuited_lookup.insert(product, rank);
-                rank += 1;
+            let mut pr = 13;
+            while pr > 0 {
+                pr -= 1;
+                if pr != i {
+                    self.insert([i, i, i, pr, pr], rank);
+                    rank += 1;
+                }
             }
         }
 
@@ -218,101 +256,97 @@ impl LookupTable {
         rank = LookupTable::MAX_STRAIGHT + 1;
 
         // pick three of one rank
-        for r in &backwards_ranks {
-            let mut kickers = backwards_ranks.clone();
-            kickers.remove(*r);
-            let gen = kickers.iter().combinations(2);
-
-            for kickers in gen {
-                let c1 = kickers[0];
-                let c2 = kickers[1];
-                let product = EvaluationCard::PRIMES[*r].pow(3)
-                    * EvaluationCard::PRIMES[*c1]
-                    * EvaluationCard::PRIMES[*c2];
-                self.unsuited_lookup.insert(product, rank);
-                rank += 1;
+        let mut r = 13;
+        while r > 0 {
+            r -= 1;
+            // and two different kickers, the higher one first
+            let mut c1 = 13;
+            while c1 > 0 {
+                c1 -= 1;
+                let mut c2 = c1;
+                while c2 > 0 {
+                    c2 -= 1;
+                    if c1 != r && c2 != r {
+                        self.insert([r, r, r, c1, c2], rank);
+                        rank += 1;
+                    }
+                }
             }
         }
 
         // 4) Two Pair
         rank = LookupTable::MAX_THREE_OF_A_KIND + 1;
 
-        let tpgen = backwards_ranks.iter().combinations(2);
-
-        for tp in tpgen {
-
-            let pair1 = tp[0];
-            let pair2 = tp[1];
-            let mut kickers = backwards_ranks.clone();
-            kickers.retain(|&x| x != *pair1 && x != *pair2);
-            for &kicker in kickers.iter() {
-
-                let product =
-                    EvaluationCard::PRIMES[*pair1].pow(2)
-                    * EvaluationCard::PRIMES[*pair2].pow(2)
-                    * EvaluationCard::PRIMES[kicker];
-                self.unsuited_lookup.insert(product, rank);
-                rank += 1;
+        let mut pair1 = 13;
+        while pair1 > 0 {
+            pair1 -= 1;
+            let mut pair2 = pair1;
+            while pair2 > 0 {
+                pair2 -= 1;
+                let mut kicker = 13;
+                while kicker > 0 {
+                    kicker -= 1;
+                    if kicker != pair1 && kicker != pair2 {
+                        self.insert([pair1, pair1, pair2, pair2, kicker], rank);
+                        rank += 1;
+                    }
+                }
             }
         }
+
         // 5) Pair
-        let mut rank = LookupTable::MAX_TWO_PAIR + 1;
+        rank = LookupTable::MAX_TWO_PAIR + 1;
 
         // choose a pair
-        for pairrank in backwards_ranks.iter() {
-            let mut kickers = backwards_ranks.clone();
-            kickers.retain(|&x| x != *pairrank);
-            let kgen = kickers.iter().combinations(3);
-
-            for kickers in kgen {
-                let (k1, k2, k3) = (kickers[0], kickers[1], kickers[2]);
-                let product = 
-                    EvaluationCard::PRIMES[*pairrank].pow(2)
-                    * EvaluationCard::PRIMES[*k1] 
-                    * EvaluationCard::PRIMES[*k2] 
-                    * EvaluationCard::PRIMES[*k3];
-                self.unsuited_lookup.insert(product, rank);
-                rank += 1;
+        let mut pairrank = 13;
+        while pairrank > 0 {
+            pairrank -= 1;
+            // and three different kickers
+            let mut k1 = 13;
+            while k1 > 0 {
+                k1 -= 1;
+                let mut k2 = k1;
+                while k2 > 0 {
+                    k2 -= 1;
+                    let mut k3 = k2;
+                    while k3 > 0 {
+                        k3 -= 1;
+                        if k1 != pairrank && k2 != pairrank && k3 != pairrank {
+                            self.insert([pairrank, pairrank, k1, k2, k3], rank);
+                            rank += 1;
+                        }
+                    }
+                }
             }
         }
     }
 
-    fn straight_and_highcards(&mut self, straights: &Vec<i32>, highcards: &Vec<i32>) {
-        LookupTable::fill_in_lookup_table(
-            LookupTable::MAX_FLUSH + 1,
-            straights,
-            &mut self.unsuited_lookup
-        );
-        LookupTable::fill_in_lookup_table(
-            LookupTable::MAX_PAIR + 1,
-            highcards,
-            &mut self.unsuited_lookup
-        );
-    }
-    
-
-    
-    
-    fn write_table_to_disk(table: &HashMap<u64, i32>, filepath: &str) -> std::io::Result<()> {
-        let file = File::create(filepath)?;
-        let mut writer = BufWriter::new(file);
-        for (prime_prod, rank) in table {
-            let line = format!("{},{}\n", prime_prod, rank);
-            writer.write(line.as_bytes())?;
+    const fn straight_and_highcards(&mut self) {
+        // Straights and high cards rank in the same order as straight
+        // flushes and flushes, from their own starting ranks.
+        let mut rankbits: usize = 1 << 13;
+        while rankbits > 0 {
+            rankbits -= 1;
+            let flush_rank = self.flush_lookup[rankbits] as i32;
+            if flush_rank == 0 {
+                continue;
+            }
+            let mut ranks = [0; 5];
+            let mut n_ranks = 0;
+            let mut r = 0;
+            while r < 13 {
+                if rankbits & 1 << r != 0 {
+                    ranks[n_ranks] = r;
+                    n_ranks += 1;
+                }
+                r += 1;
+            }
+            let rank = if flush_rank <= LookupTable::MAX_STRAIGHT_FLUSH {
+                LookupTable::MAX_FLUSH + flush_rank
+            } else {
+                LookupTable::MAX_PAIR + flush_rank - LookupTable::MAX_FULL_HOUSE
+            };
+            self.insert(ranks, rank);
         }
-        writer.flush()?;
-        Ok(())
     }
-
-    fn get_lexographically_next_bit_sequence(bits: i32) -> impl Iterator<Item = i32> {
-        let t = (bits | (bits - 1)) + 1;
-        let mut next = t | ((((t & -t) / (bits & -bits)) >> 1) - 1);
-        std::iter::from_fn(move || {
-            let result = next;
-            let t = (next | (next - 1)) + 1;
-            next = t | ((((t & -t) / (next & -next)) >> 1) - 1);
-            Some(result)
-        })
-    }
-                
-}
\ No newline at end of file
+}
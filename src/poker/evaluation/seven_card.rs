@@ -0,0 +1,178 @@
+/// Direct lookup of six and seven card hands, instead of taking the best of
+/// their five card subsets. Two facts make this possible:
+///
+/// - With five or more cards of one suit no full house or four of a kind is
+///   possible, so the hand is the best flush in that suit's ranks. Flushes
+///   are looked up by the suit's 13 rankbits.
+/// - Otherwise suits do not matter and the hand is its multiset of ranks, at
+///   most four of each. There are 18395 of these for six cards and 49205 for
+///   seven, and each gets a dense index: its position in lexicographic order
+///   of rank counts.
+///
+/// Both tables hold the same ranks as `LookupTable` and are filled from it
+/// once, by brute force over the five card subsets.
+use std::sync::OnceLock;
+
+use itertools::Itertools;
+
+use super::lookup::{rank_index, LookupTable, LOOKUP_TABLE, N_RANK_MULTISETS};
+
+const N_RANKS: usize = 13;
+const N_CARDS: usize = 7;
+
+/// The cards of a hand so far, as the rankbits of each suit and the count of
+/// each rank, which is all a lookup needs. It is `Copy`, so the board can be
+/// gathered once and each player's cards added to a copy of it. Cards must be
+/// distinct; that is not checked.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PartialHand {
+    suit_rankbits: [usize; 4],
+    counts: [u8; N_RANKS],
+    n_cards: usize,
+}
+
+impl PartialHand {
+    pub fn from_mask(cards: u64) -> PartialHand {
+        let mut hand = PartialHand::default();
+        hand.add_mask(cards);
+        hand
+    }
+
+    pub fn from_eval_cards(cards: &[i32]) -> PartialHand {
+        // Cards in the `EvaluationCard` layout.
+        let mut hand = PartialHand::default();
+        for &card in cards {
+            let suit = ((card >> 12) & 0xF).trailing_zeros() as usize;
+            hand.add_rank(((card >> 8) & 0xF) as usize, suit);
+        }
+        hand
+    }
+
+    pub fn n_cards(&self) -> usize {
+        self.n_cards
+    }
+
+    pub fn add(&mut self, card: u8) {
+        // A card as from `Card::to_u8`.
+        self.add_rank((card / 4) as usize, (card % 4) as usize);
+    }
+
+    pub fn add_mask(&mut self, mut cards: u64) {
+        while cards != 0 {
+            self.add(cards.trailing_zeros() as u8);
+            cards &= cards - 1;
+        }
+    }
+
+    fn add_rank(&mut self, rank: usize, suit: usize) {
+        self.suit_rankbits[suit] |= 1 << rank;
+        self.counts[rank] += 1;
+        self.n_cards += 1;
+    }
+}
+
+pub struct SevenCardTable {
+    // The best flush in each set of five to seven suited ranks.
+    flushes: Vec<i32>,
+    // The best hand of each multiset of six and of seven ranks, by
+    // `rank_index`.
+    unsuited_six: Vec<i32>,
+    unsuited_seven: Vec<i32>,
+}
+
+impl SevenCardTable {
+    pub fn new(table: &LookupTable) -> SevenCardTable {
+        let mut flushes = vec![0; 1 << N_RANKS];
+        for (rankbits, rank) in flushes.iter_mut().enumerate() {
+            if (5..=N_CARDS as u32).contains(&rankbits.count_ones()) {
+                let ranks: Vec<i32> = (0..N_RANKS as i32).filter(|r| rankbits & 1 << r != 0).collect();
+                *rank = ranks
+                    .iter()
+                    .combinations(5)
+                    .map(|combo| table.flush_rank(combo.iter().fold(0, |bits, &&r| bits | 1 << r)))
+                    .min()
+                    .unwrap();
+            }
+        }
+
+        SevenCardTable {
+            flushes,
+            unsuited_six: SevenCardTable::best_unsuited(table, 6),
+            unsuited_seven: SevenCardTable::best_unsuited(table, 7),
+        }
+    }
+
+    fn best_unsuited(table: &LookupTable, n_cards: usize) -> Vec<i32> {
+        let mut unsuited = vec![0; N_RANK_MULTISETS[N_RANKS][n_cards]];
+        for ranks in (0..N_RANKS).combinations_with_replacement(n_cards) {
+            let mut counts = [0u8; N_RANKS];
+            for &r in &ranks {
+                counts[r] += 1;
+            }
+            if counts.iter().any(|&count| count > 4) {
+                continue;
+            }
+            unsuited[rank_index(&counts, n_cards)] = ranks
+                .iter()
+                .combinations(5)
+                .map(|combo| {
+                    let mut counts = [0u8; N_RANKS];
+                    for &&r in &combo {
+                        counts[r] += 1;
+                    }
+                    table.unsuited_rank(&counts)
+                })
+                .min()
+                .unwrap();
+        }
+        unsuited
+    }
+
+    pub fn shared() -> &'static SevenCardTable {
+        // Filling the tables takes a moment, so it is done once per process.
+        static TABLE: OnceLock<SevenCardTable> = OnceLock::new();
+        TABLE.get_or_init(|| SevenCardTable::new(&LOOKUP_TABLE))
+    }
+
+    pub fn evaluate(&self, hand: &PartialHand) -> i32 {
+        let flush_rankbits = hand.suit_rankbits.iter().find(|rankbits| rankbits.count_ones() >= 5);
+        match (flush_rankbits, hand.n_cards) {
+            (Some(&rankbits), 5..=7) => self.flushes[rankbits],
+            (None, 5) => LOOKUP_TABLE.unsuited_rank(&hand.counts),
+            (None, 6) => self.unsuited_six[rank_index(&hand.counts, 6)],
+            (None, 7) => self.unsuited_seven[rank_index(&hand.counts, 7)],
+            _ => panic!("number of cards on table is not 5, 6 or 7"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::index::sample;
+    use rand::thread_rng;
+
+    use super::*;
+    use crate::poker::card::Card;
+    use crate::poker::evaluation::evaluator::Evaluator;
+
+    #[test]
+    fn matches_best_five_card_subset() {
+        let evaluator = Evaluator::new();
+        let table = SevenCardTable::shared();
+        let mut rng = thread_rng();
+        for n_cards in [6, 7] {
+            for _ in 0..3000 {
+                let cards: Vec<Card> =
+                    sample(&mut rng, 52, n_cards).iter().map(|card| Card::from_u8(card as u8)).collect();
+                let best = cards
+                    .iter()
+                    .cloned()
+                    .combinations(5)
+                    .map(|hand| evaluator.evaluate(&hand[..2].to_vec(), &hand[2..].to_vec()))
+                    .min()
+                    .unwrap();
+                assert_eq!(table.evaluate(&PartialHand::from_mask(Card::to_mask(&cards))), best, "{:?}", cards);
+            }
+        }
+    }
+}
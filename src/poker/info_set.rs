@@ -0,0 +1,116 @@
+/// A compact information set key for regret tables. The first part describes
+/// the cards: the card abstraction bucket as four little endian bytes, or
+/// without a card abstraction the private cards sorted, then the flop sorted
+/// and the turn and river, one byte per card as given by `Card::to_u8`. The
+/// rest is the abstract betting history: for each action its index among the
+/// abstract actions legal at the time, with `STREET_END` closing each finished
+/// betting round.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InfoSetKey(Box<[u8]>);
+
+impl InfoSetKey {
+    pub const STREET_END: u8 = u8::MAX;
+
+    pub fn from_bucket(bucket: u32, betting: &[u8]) -> Self {
+        let mut bytes = bucket.to_le_bytes().to_vec();
+        bytes.extend_from_slice(betting);
+        InfoSetKey(bytes.into_boxed_slice())
+    }
+
+    pub fn from_cards(hand: &[u8], board: &[u8], betting: &[u8]) -> Self {
+        let mut hand = hand.to_vec();
+        hand.sort();
+        let mut board = board.to_vec();
+        if board.len() >= 3 {
+            board[..3].sort();
+        }
+        let mut bytes = hand;
+        bytes.extend(board);
+        bytes.extend_from_slice(betting);
+        InfoSetKey(bytes.into_boxed_slice())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        InfoSetKey(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn extended(&self, bytes: &[u8]) -> Self {
+        // This key followed by more bytes, to tell apart decisions that share
+        // the same cards and betting.
+        let mut extended = self.0.to_vec();
+        extended.extend_from_slice(bytes);
+        InfoSetKey(extended.into_boxed_slice())
+    }
+}
+
+impl AsRef<[u8]> for InfoSetKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<&[u8]> for InfoSetKey {
+    fn from(bytes: &[u8]) -> Self {
+        InfoSetKey::from_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::actions::Action;
+    use crate::poker::deck::Deck;
+    use crate::poker::state::PokerGameState;
+
+    fn flop_after_raise_and_call() -> PokerGameState {
+        // Heads up with blinds of 50 and 100: the small blind raises to 300,
+        // the big blind calls, and the flop is dealt.
+        let state = PokerGameState::new_hand(&[1000, 1000], &Deck::new(None, None), 50, 100).sample_chance_outcome();
+        let state = state.apply_action(&Action::RaiseTo(300)).apply_action(&Action::Call);
+        state.sample_chance_outcome()
+    }
+
+    fn betting(key: &InfoSetKey, n_cards: usize) -> &[u8] {
+        &key.as_bytes()[n_cards..]
+    }
+
+    #[test]
+    fn cards_then_betting() {
+        let key = InfoSetKey::from_cards(&[40, 3], &[30, 12, 20, 7, 50], &[1, InfoSetKey::STREET_END, 0]);
+        // Hand and flop sorted, turn and river in order.
+        assert_eq!(key.as_bytes(), [3, 40, 12, 20, 30, 7, 50, 1, InfoSetKey::STREET_END, 0]);
+        let key = InfoSetKey::from_bucket(0x01020304, &[2]);
+        assert_eq!(key.as_bytes(), [4, 3, 2, 1, 2]);
+        assert_eq!(InfoSetKey::from_bytes(key.as_bytes()), key);
+    }
+
+    #[test]
+    fn street_end_closes_each_round() {
+        // Pre-flop the raise is the fourth abstract action (fold, call, half
+        // pot, pot) and the call the second, then the big blind checks on
+        // the flop and the small blind checks behind.
+        let flop = flop_after_raise_and_call();
+        let key = flop.info_set();
+        assert_eq!(betting(&key, 5), [3, 1, InfoSetKey::STREET_END]);
+        let turn = flop.apply_action(&Action::Check).apply_action(&Action::Check).sample_chance_outcome();
+        assert_eq!(betting(&turn.info_set(), 6), [3, 1, InfoSetKey::STREET_END, 0, 0, InfoSetKey::STREET_END]);
+    }
+
+    #[test]
+    fn off_tree_sizes_share_a_key() {
+        // Bets of 250 and 260 into 600 both translate to the half pot bet,
+        // so the caller sees the same information set, though the real
+        // histories differ.
+        let flop = flop_after_raise_and_call().apply_action(&Action::Check);
+        let after_250 = flop.apply_action(&Action::Bet(250));
+        let after_260 = flop.apply_action(&Action::Bet(260));
+        assert_ne!(after_250.betting_sequence(), after_260.betting_sequence());
+        assert_eq!(after_250.info_set(), after_260.info_set());
+        assert_eq!(after_250.info_set(), flop.apply_action(&Action::Bet(300)).info_set());
+        assert_ne!(after_250.info_set(), flop.apply_action(&Action::Bet(600)).info_set());
+    }
+}
@@ -0,0 +1,317 @@
+/// A range of hole cards: a weight from 0 to 1 for each of the 1326 two card
+/// combos, where 0 means the combo is not in the range and fractions mean it
+/// is played only some of the time.
+///
+/// Ranges parse from the usual comma separated notation, with an optional
+/// `:weight` after any part:
+/// ```text
+/// AA           one pair, all 6 combos
+/// AKs, AKo     suited (4 combos) or offsuit (12); AK is both
+/// TT+          TT and every better pair
+/// A2s+         A2s up to AKs, raising the kicker
+/// A2s-A5s      A2s, A3s, A4s and A5s; 22-55 for pairs
+/// AsKh         one combo, suits s, h, d, c
+/// KQo:0.5      every KQo combo at half weight
+/// ```
+/// A later part overrides the weights set by an earlier one. Set operations
+/// treat weights as degrees of membership: union takes the larger weight,
+/// intersection the smaller, and difference subtracts.
+use super::card::Card;
+use super::equity::HoleCards;
+
+const N_COMBOS: usize = 1326;
+const RANKS: &str = "23456789TJQKA";
+const SUITS: &str = "sdch";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    // By `combo_index`.
+    weights: Vec<f64>,
+}
+
+impl Default for Range {
+    fn default() -> Self {
+        Range::new()
+    }
+}
+
+impl Range {
+    pub fn new() -> Self {
+        // The empty range.
+        Range {
+            weights: vec![0.0; N_COMBOS],
+        }
+    }
+
+    pub fn full() -> Self {
+        Range {
+            weights: vec![1.0; N_COMBOS],
+        }
+    }
+
+    pub fn from_hands(hands: &[[Card; 2]]) -> Self {
+        let mut range = Range::new();
+        for hand in hands {
+            range.set_weight(hand, 1.0);
+        }
+        range
+    }
+
+    pub fn parse(notation: &str) -> Result<Self, String> {
+        let mut range = Range::new();
+        for part in notation.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            let error = |message: &str| format!("{} in '{}'", message, part);
+            let (hands, weight) = match part.split_once(':') {
+                Some((hands, weight)) => match weight.trim().parse::<f64>() {
+                    Ok(weight) if (0.0..=1.0).contains(&weight) => (hands.trim(), weight),
+                    _ => return Err(error("weight must be a number from 0 to 1")),
+                },
+                None => (part, 1.0),
+            };
+            for (card_a, card_b) in parse_hands(hands).map_err(|message| error(&message))? {
+                range.weights[combo_index(card_a, card_b)] = weight;
+            }
+        }
+        Ok(range)
+    }
+
+    pub fn weight(&self, hand: &[Card; 2]) -> f64 {
+        self.weights[combo_index(hand[0].to_u8(), hand[1].to_u8())]
+    }
+
+    pub fn set_weight(&mut self, hand: &[Card; 2], weight: f64) {
+        let (card_a, card_b) = (hand[0].to_u8(), hand[1].to_u8());
+        if card_a == card_b {
+            panic!("A hand can not hold the same card twice");
+        }
+        self.weights[combo_index(card_a, card_b)] = weight;
+    }
+
+    pub fn contains(&self, hand: &[Card; 2]) -> bool {
+        self.weight(hand) > 0.0
+    }
+
+    pub fn n_combos(&self) -> usize {
+        self.weights.iter().filter(|&&weight| weight > 0.0).count()
+    }
+
+    pub fn total_weight(&self) -> f64 {
+        // Combos counted by weight, so KQo:0.5 counts 6.
+        self.weights.iter().sum()
+    }
+
+    pub fn combos(&self) -> Vec<([Card; 2], f64)> {
+        // Combos in the range with their weights, lowest cards first.
+        combo_cards()
+            .zip(&self.weights)
+            .filter(|&(_, &weight)| weight > 0.0)
+            .map(|((card_a, card_b), &weight)| ([Card::from_u8(card_a), Card::from_u8(card_b)], weight))
+            .collect()
+    }
+
+    pub fn remove_cards(&mut self, cards: &[Card]) {
+        // Drops every combo holding one of the cards, such as the board or
+        // an opponent's known hand.
+        let blocked = Card::to_mask(cards);
+        for ((card_a, card_b), weight) in combo_cards().zip(self.weights.iter_mut()) {
+            if blocked & (1 << card_a | 1 << card_b) != 0 {
+                *weight = 0.0;
+            }
+        }
+    }
+
+    pub fn union(&self, other: &Range) -> Range {
+        self.combine(other, f64::max)
+    }
+
+    pub fn intersection(&self, other: &Range) -> Range {
+        self.combine(other, f64::min)
+    }
+
+    pub fn difference(&self, other: &Range) -> Range {
+        self.combine(other, |weight, other_weight| (weight - other_weight).max(0.0))
+    }
+
+    fn combine(&self, other: &Range, f: impl Fn(f64, f64) -> f64) -> Range {
+        Range {
+            weights: self.weights.iter().zip(&other.weights).map(|(&a, &b)| f(a, b)).collect(),
+        }
+    }
+}
+
+impl From<&Range> for HoleCards {
+    fn from(range: &Range) -> Self {
+        HoleCards::Range(range.combos())
+    }
+}
+
+fn combo_index(card_a: u8, card_b: u8) -> usize {
+    // Combos ordered by their higher card, then their lower card.
+    let (low, high) = (card_a.min(card_b) as usize, card_a.max(card_b) as usize);
+    high * (high - 1) / 2 + low
+}
+
+fn combo_cards() -> impl Iterator<Item = (u8, u8)> {
+    // Every combo as `Card::to_u8` bytes, in `combo_index` order.
+    (1..52u8).flat_map(|high| (0..high).map(move |low| (low, high)))
+}
+
+fn parse_hands(hands: &str) -> Result<Vec<(u8, u8)>, String> {
+    // One part of a range without its weight, as pairs of card bytes.
+    if let [rank_a, suit_a, rank_b, suit_b] = hands.chars().collect::<Vec<char>>()[..] {
+        if let (Some(suit_a), Some(suit_b)) = (parse_suit(suit_a), parse_suit(suit_b)) {
+            let card_a = 4 * parse_rank(rank_a)? + suit_a;
+            let card_b = 4 * parse_rank(rank_b)? + suit_b;
+            if card_a == card_b {
+                return Err("a hand can not hold the same card twice".to_string());
+            }
+            return Ok(vec![(card_a, card_b)]);
+        }
+    }
+
+    let classes = if let Some(class) = hands.strip_suffix('+') {
+        let class = HandClass::parse(class)?;
+        if class.is_pair() {
+            (class.high..13).map(|rank| HandClass { high: rank, low: rank, ..class }).collect()
+        } else {
+            (class.low..class.high).map(|low| HandClass { low, ..class }).collect()
+        }
+    } else if let Some((first, last)) = hands.split_once('-') {
+        let (first, last) = (HandClass::parse(first)?, HandClass::parse(last)?);
+        if first.is_pair() && last.is_pair() {
+            let (from, to) = (first.high.min(last.high), first.high.max(last.high));
+            (from..=to).map(|rank| HandClass { high: rank, low: rank, ..first }).collect()
+        } else if first.high == last.high && first.suited == last.suited && !first.is_pair() && !last.is_pair() {
+            let (from, to) = (first.low.min(last.low), first.low.max(last.low));
+            (from..=to).map(|low| HandClass { low, ..first }).collect()
+        } else {
+            return Err("a dash must join two pairs, or hands with the same top card and suits".to_string());
+        }
+    } else {
+        vec![HandClass::parse(hands)?]
+    };
+    Ok(classes.iter().flat_map(HandClass::combos).collect())
+}
+
+/// Hands by their ranks alone, such as AKs, with ranks from 0 for a deuce.
+/// `suited` is None for both suited and offsuit hands, as in AK.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct HandClass {
+    high: u8,
+    low: u8,
+    suited: Option<bool>,
+}
+
+impl HandClass {
+    fn parse(class: &str) -> Result<Self, String> {
+        let chars: Vec<char> = class.trim().chars().collect();
+        let (rank_a, rank_b, suited) = match chars[..] {
+            [rank_a, rank_b] => (parse_rank(rank_a)?, parse_rank(rank_b)?, None),
+            [rank_a, rank_b, 's'] => (parse_rank(rank_a)?, parse_rank(rank_b)?, Some(true)),
+            [rank_a, rank_b, 'o'] => (parse_rank(rank_a)?, parse_rank(rank_b)?, Some(false)),
+            _ => return Err(format!("unknown hand '{}'", class)),
+        };
+        if rank_a == rank_b && suited.is_some() {
+            return Err("a pair can not be suited or offsuit".to_string());
+        }
+        Ok(HandClass {
+            high: rank_a.max(rank_b),
+            low: rank_a.min(rank_b),
+            suited,
+        })
+    }
+
+    fn is_pair(&self) -> bool {
+        self.high == self.low
+    }
+
+    fn combos(&self) -> Vec<(u8, u8)> {
+        let mut combos = Vec::new();
+        for suit_a in 0..4 {
+            for suit_b in 0..4 {
+                let is_suited = suit_a == suit_b;
+                let is_wanted = if self.is_pair() {
+                    suit_a < suit_b
+                } else {
+                    self.suited.is_none_or(|suited| suited == is_suited)
+                };
+                if is_wanted {
+                    combos.push((4 * self.high + suit_a, 4 * self.low + suit_b));
+                }
+            }
+        }
+        combos
+    }
+}
+
+fn parse_rank(rank: char) -> Result<u8, String> {
+    RANKS
+        .find(rank.to_ascii_uppercase())
+        .map(|rank_i| rank_i as u8)
+        .ok_or_else(|| format!("unknown rank '{}'", rank))
+}
+
+fn parse_suit(suit: char) -> Option<u8> {
+    SUITS.find(suit).map(|suit_i| suit_i as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poker::equity::{equity, Method};
+
+    #[test]
+    fn parse_counts_combos() {
+        let range = Range::parse("AKs, TT+, A2s-A5s, KQo:0.5").unwrap();
+        // 4 + 30 + 16 + 12 combos, the offsuit kings and queens at half weight.
+        assert_eq!(range.n_combos(), 62);
+        assert_eq!(range.total_weight(), 56.0);
+        assert_eq!(Range::parse("KQo+").unwrap().n_combos(), 12);
+        assert_eq!(Range::parse("A2s+").unwrap().n_combos(), 48);
+        assert_eq!(Range::parse("22-55").unwrap().n_combos(), 24);
+        assert_eq!(Range::parse("AK").unwrap().n_combos(), 16);
+
+        let range = Range::parse("AsKh").unwrap();
+        assert_eq!(range.n_combos(), 1);
+        assert!(range.contains(&[Card::new("K", "hearts"), Card::new("A", "spades")]));
+    }
+
+    #[test]
+    fn parse_rejects_bad_ranges() {
+        for notation in ["AhAh", "AA:1.5", "AKs-A2o", "AAs", "XK", "AK-"] {
+            assert!(Range::parse(notation).is_err(), "'{}' parsed", notation);
+        }
+    }
+
+    #[test]
+    fn later_parts_override_weights() {
+        let range = Range::parse("AA, AsAh:0.25").unwrap();
+        assert_eq!(range.weight(&[Card::new("A", "spades"), Card::new("A", "hearts")]), 0.25);
+        assert_eq!(range.total_weight(), 5.25);
+    }
+
+    #[test]
+    fn set_operations() {
+        let pairs = Range::parse("TT+").unwrap();
+        let aces = Range::parse("AA:0.5").unwrap();
+        assert_eq!(pairs.union(&aces), pairs);
+        assert_eq!(pairs.intersection(&aces), aces);
+        assert_eq!(pairs.difference(&aces).total_weight(), 27.0);
+
+        let mut range = pairs.clone();
+        range.remove_cards(&[Card::new("A", "spades")]);
+        assert_eq!(range.n_combos(), 27);
+    }
+
+    #[test]
+    fn ranges_feed_equity() {
+        // Single combo ranges agree with the known hands, AA against KK on a
+        // 2c7d9h flop.
+        let aces = Range::parse("AsAh").unwrap();
+        let kings = Range::parse("KsKh").unwrap();
+        let board = [Card::new("2", "clubs"), Card::new("7", "diamonds"), Card::new("9", "hearts")];
+        let result = equity(&[HoleCards::from(&aces), HoleCards::from(&kings)], &board, &[], Method::Exact);
+        assert_eq!(result.n_deals, 990);
+        assert!((result.players[0].equity - 907.0 / 990.0).abs() < 1e-12);
+    }
+}
@@ -1,52 +1,739 @@
-use super::game::PokerGame;
-use super::player::Player;
+use itertools::Itertools;
 
-use std::rc::Rc;
+use super::action_abstraction::ActionAbstraction;
+use super::action_translation::translate;
+use super::actions::{legal_actions, Action};
+use super::card::Card;
+use super::deck::Deck;
+use super::evaluation::evaluator::Evaluator;
+use super::info_set::InfoSetKey;
+use super::strategy::Observation;
+use super::table::{Table, MAX_PLAYERS};
+use crate::cfr::game_state::{ChanceOutcomes, GameState};
+use crate::clustering::card_abstraction::CardAbstraction;
 
+use std::sync::Arc;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BettingStage {
+    PreFlop,
+    Flop,
+    Turn,
+    River,
+}
+
+impl BettingStage {
+    fn next(&self) -> Option<BettingStage> {
+        match self {
+            BettingStage::PreFlop => Some(BettingStage::Flop),
+            BettingStage::Flop => Some(BettingStage::Turn),
+            BettingStage::Turn => Some(BettingStage::River),
+            BettingStage::River => None,
+        }
+    }
+
+    fn from_n_community_cards(n_cards: usize) -> BettingStage {
+        match n_cards {
+            0 => BettingStage::PreFlop,
+            3 => BettingStage::Flop,
+            4 => BettingStage::Turn,
+            5 => BettingStage::River,
+            _ => panic!("No street has {} community cards", n_cards),
+        }
+    }
+
+    fn n_community_cards(&self) -> usize {
+        match self {
+            BettingStage::PreFlop => 0,
+            BettingStage::Flop => 3,
+            BettingStage::Turn => 4,
+            BettingStage::River => 5,
+        }
+    }
+}
+
+/// The actions of a hand so far, per street, both as taken and translated onto
+/// the action abstraction, and the abstract actions' indices that key
+/// information sets.
+#[derive(Clone, Debug)]
+struct BettingHistory {
+    actions: Vec<Vec<Action>>,
+    abstract_actions: Vec<Vec<Action>>,
+    betting_key: Vec<u8>,
+}
+
+impl BettingHistory {
+    fn new() -> Self {
+        BettingHistory {
+            actions: vec![Vec::new()],
+            abstract_actions: vec![Vec::new()],
+            betting_key: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, action: Action, abstract_action: Action, abstract_i: usize) {
+        self.actions.last_mut().unwrap().push(action);
+        self.abstract_actions.last_mut().unwrap().push(abstract_action);
+        self.betting_key.push(abstract_i as u8);
+    }
+
+    fn end_street(&mut self) {
+        self.actions.push(Vec::new());
+        self.abstract_actions.push(Vec::new());
+        self.betting_key.push(InfoSetKey::STREET_END);
+    }
+}
+
+/// One node of a hand of no-limit hold'em. Dealing is modelled with chance
+/// nodes. A raise must be at least the size of the last full raise of the
+/// round (the big blind if there was none) unless it puts the player all in;
+/// an all in for less than a full raise does not reopen the betting to players
+/// who have already acted. Pre-flop the big blind keeps the option to raise
+/// when everyone just calls.
+///
+/// `legal_actions` offers the bets and raises of the state's action
+/// abstraction, while `apply_action` accepts any action allowed by
+/// `actions::legal_actions`. Each action is also recorded translated onto the
+/// abstraction, and information sets are keyed by that abstract history and
+/// either the cards or, given a card abstraction, their bucket.
+/// Every state links back to the state it came from.
+///
+/// Chips and cards live in a `Table` of plain values, and the betting history
+/// and everything shared are behind an `Arc`, so states are `Send + Sync` and
+/// cloning one, or linking a child back to it, copies no vectors. A child
+/// copies the betting history once, when it records an action or a new street.
+#[derive(Clone)]
 pub struct PokerGameState {
-    previous_state: Option<Rc<PokerGameState>>,
-    game: PokerGame,
-    player: Option<Rc<Player>>,
-    action: Option<String>,
+    previous_state: Option<Arc<PokerGameState>>,
+    table: Table,
+    // Every card of the deck the hand is dealt from.
+    deck: u64,
+    is_dealt: bool,
+    player_i: usize,
+    action: Option<Action>,
+    abstract_action: Option<Action>,
     is_terminal: bool,
+    betting_stage: BettingStage,
+    big_blind: i32,
+    n_raises: u32,
+    min_raise: i32,
+    street_start_bet: i32,
+    n_players_to_act: usize,
+    can_raise: [bool; MAX_PLAYERS],
+    initial_chips: [i32; MAX_PLAYERS],
+    history: Arc<BettingHistory>,
+    action_abstraction: Arc<ActionAbstraction>,
+    card_abstraction: Option<Arc<CardAbstraction>>,
+    evaluator: Arc<Evaluator>,
 }
 
 impl PokerGameState {
-    pub fn new_hand(game: PokerGame) -> Self {
+    pub fn new_hand(stacks: &[i32], deck: &Deck, small_blind: i32, big_blind: i32) -> Self {
+        let mut table = Table::new(stacks, deck);
+        for (player_i, &n_chips) in stacks.iter().enumerate() {
+            // Players without chips sit the hand out.
+            table.is_folded[player_i] = n_chips == 0;
+        }
+        let initial_chips = table.stacks;
+        table.add_to_pot(0, small_blind);
+        table.add_to_pot(1, big_blind);
+
         PokerGameState {
             previous_state: None,
-            game,
-            player: None,
+            table,
+            deck: Table::deck_mask(deck),
+            is_dealt: false,
+            player_i: 0,
             action: None,
+            abstract_action: None,
             is_terminal: false,
+            betting_stage: BettingStage::PreFlop,
+            big_blind,
+            n_raises: 0,
+            min_raise: big_blind,
+            street_start_bet: 0,
+            n_players_to_act: 0,
+            can_raise: [false; MAX_PLAYERS],
+            initial_chips,
+            history: Arc::new(BettingHistory::new()),
+            action_abstraction: Arc::new(ActionAbstraction::default()),
+            card_abstraction: None,
+            evaluator: Arc::new(Evaluator::new()),
         }
     }
 
-    // pub fn table(&self) -> PokerTable {
-    //     self.table
-    // }
+    pub fn set_action_abstraction(&mut self, action_abstraction: Arc<ActionAbstraction>) {
+        self.action_abstraction = action_abstraction;
+    }
+
+    pub fn set_card_abstraction(&mut self, card_abstraction: Arc<CardAbstraction>) {
+        self.card_abstraction = Some(card_abstraction);
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn n_players(&self) -> usize {
+        self.table.n_players()
+    }
+
+    pub fn action(&self) -> Option<Action> {
+        self.action
+    }
+
+    pub fn abstract_action(&self) -> Option<Action> {
+        // The action that led to this state, translated onto the abstraction.
+        self.abstract_action
+    }
+
+    pub fn deck(&self) -> u64 {
+        // Every card of the deck the hand is dealt from.
+        self.deck
+    }
+
+    pub fn betting_stage(&self) -> BettingStage {
+        self.betting_stage
+    }
 
     pub fn is_terminal(&self) -> bool {
         self.is_terminal
     }
 
     pub fn is_chance_node(&self) -> bool {
-        false
+        !self.is_terminal && self.n_cards_to_deal() > 0
+    }
+
+    pub fn is_decision_node(&self) -> bool {
+        !self.is_terminal && !self.is_chance_node()
+    }
+
+    pub fn player_i(&self) -> usize {
+        self.player_i
+    }
+
+    pub fn min_raise(&self) -> i32 {
+        // The size of the last full raise this round, or the big blind.
+        self.min_raise
+    }
+
+    pub fn can_raise(&self, player_i: usize) -> bool {
+        // False once a player has acted, until someone makes a full raise.
+        self.can_raise[player_i]
+    }
+
+    pub fn n_raises(&self) -> u32 {
+        // Bets and raises made so far this betting round.
+        self.n_raises
+    }
+
+    pub fn street_bet(&self) -> i32 {
+        // The biggest bet of this betting round.
+        self.biggest_bet() - self.street_start_bet
+    }
+
+    pub fn street_start_bet(&self) -> i32 {
+        // The hand contribution of the players still able to act when this
+        // betting round started.
+        self.street_start_bet
+    }
+
+    pub fn legal_actions(&self) -> Vec<Action> {
+        self.action_abstraction.legal_actions(self)
+    }
+
+    pub fn is_legal(&self, action: &Action) -> bool {
+        let legal = legal_actions(self);
+        let is_opened = self.biggest_bet() > self.street_start_bet;
+        match *action {
+            Action::Fold => legal.fold,
+            Action::Check => legal.check,
+            Action::Call => legal.call.is_some(),
+            Action::Bet(n_chips) => !is_opened && legal.raise.is_some_and(|raise| raise.contains(n_chips)),
+            Action::RaiseTo(n_chips) => is_opened && legal.raise.is_some_and(|raise| raise.contains(n_chips)),
+            Action::AllIn => legal.raise.is_some() || legal.call.is_some(),
+        }
+    }
+
+    pub fn apply_action(&self, action: &Action) -> PokerGameState {
+        self.apply_translated_action(action, &translate(self, action))
+    }
+
+    pub fn apply_translated_action(&self, action: &Action, abstract_action: &Action) -> PokerGameState {
+        // Applies `action` but records it as `abstract_action`, for replaying
+        // a hand with the translations it was first played with.
+        if !self.is_legal(action) {
+            panic!("Illegal action '{}', the minimum raise is {}", action, self.min_raise);
+        }
+        let legal = legal_actions(self);
+        let abstract_action = *abstract_action;
+        let abstract_i = match self.legal_actions().iter().position(|a| *a == abstract_action) {
+            Some(abstract_i) => abstract_i,
+            None => panic!("Action '{}' is not in the action abstraction", abstract_action),
+        };
+        let mut state = self.child();
+        state.action = Some(*action);
+        state.abstract_action = Some(abstract_action);
+        Arc::make_mut(&mut state.history).push(*action, abstract_action, abstract_i);
+
+        let player_i = state.player_i;
+        let biggest_bet = state.biggest_bet();
+        let street_bet = biggest_bet - state.street_start_bet;
+        let n_chips_to_call = biggest_bet - state.table.bets[player_i];
+        let player_street_bet = state.table.bets[player_i] - state.street_start_bet;
+        // The total bet for the round the player raises to, if they raise.
+        let raise_to = match *action {
+            Action::Fold => {
+                state.table.is_folded[player_i] = true;
+                None
+            }
+            Action::Check => None,
+            Action::Call => {
+                state.table.add_to_pot(player_i, n_chips_to_call);
+                None
+            }
+            Action::Bet(n_chips) | Action::RaiseTo(n_chips) => {
+                state.table.add_to_pot(player_i, n_chips - player_street_bet);
+                Some(n_chips)
+            }
+            Action::AllIn => match legal.raise {
+                Some(raise) => {
+                    state.table.add_to_pot(player_i, raise.max_to - player_street_bet);
+                    Some(raise.max_to)
+                }
+                None => {
+                    state.table.add_to_pot(player_i, n_chips_to_call);
+                    None
+                }
+            },
+        };
+
+        state.can_raise[player_i] = false;
+        if let Some(n_chips) = raise_to {
+            // Everyone else still able to act has to respond to a raise, but
+            // only a full raise lets players who already acted raise again.
+            let n_chips_raised = n_chips - street_bet;
+            state.n_raises += 1;
+            state.n_players_to_act = (0..state.n_players())
+                .filter(|&other_i| other_i != player_i && state.has_moves(other_i))
+                .count();
+            if n_chips_raised >= state.min_raise {
+                state.min_raise = n_chips_raised;
+                for (other_i, can_raise) in state.can_raise.iter_mut().enumerate() {
+                    *can_raise = other_i != player_i;
+                }
+            }
+        } else {
+            state.n_players_to_act -= 1;
+        }
+
+        if state.n_active_players() == 1 {
+            state.settle();
+        } else if state.n_players_to_act == 0 {
+            state.end_betting_round();
+        } else {
+            state.player_i = state.next_player_with_moves(player_i + 1);
+        }
+        state
+    }
+
+    pub fn deal_private_cards(&self, hands: &[[u8; 2]]) -> PokerGameState {
+        // This state with `hands` as the players' private cards, and the deck
+        // left holding every card neither they nor the board hold.
+        let mut state = self.clone();
+        state.table.set_deck(self.deck);
+        for (player_i, hand) in hands.iter().enumerate() {
+            state.table.private_cards[player_i] = *hand;
+        }
+        for &card in hands.iter().flatten().chain(self.table.board()) {
+            state.table.remove_from_deck(card);
+        }
+        state
+    }
+
+    pub fn sample_chance_outcome(&self) -> PokerGameState {
+        let mut table = self.table;
+        let cards: Vec<u8> = (0..self.n_cards_to_deal()).map(|_| table.deal_card()).collect();
+        self.deal(&cards)
+    }
+
+    pub fn chance_outcomes(&self) -> Vec<(PokerGameState, f64)> {
+        // Every way the next cards can fall, all equally likely. Pre-flop that
+        // is every way to split two card hands among the players, seat by seat.
+        let deck: Vec<u8> = (0..64).filter(|&card| self.table.deck() & 1 << card != 0).collect();
+        let deals: Vec<Vec<u8>> = if self.betting_stage == BettingStage::PreFlop {
+            PokerGameState::private_card_deals(&deck, self.n_players())
+        } else {
+            deck.into_iter().combinations(self.n_cards_to_deal()).collect()
+        };
+        let probability = 1.0 / deals.len() as f64;
+        deals.iter().map(|cards| (self.deal(cards), probability)).collect()
+    }
+
+    pub fn observation(&self) -> Observation {
+        // The current player's view of the hand.
+        let n_players = self.n_players();
+        let bets = self.table.bets[..n_players].to_vec();
+        Observation {
+            player_i: self.player_i,
+            betting_stage: self.betting_stage,
+            private_cards: self.private_cards(self.player_i),
+            community_cards: self.community_cards(),
+            stacks: self.table.stacks[..n_players].to_vec(),
+            n_chips_to_call: self.biggest_bet() - bets[self.player_i],
+            bets,
+            is_active: (0..n_players).map(|player_i| self.table.is_active(player_i)).collect(),
+            history: self.history.actions.clone(),
+            legal_actions: self.legal_actions(),
+            legal_ranges: legal_actions(self),
+            info_set: self.info_set(),
+        }
+    }
+
+    pub fn private_cards(&self, player_i: usize) -> Vec<Card> {
+        self.table.private_cards[player_i].iter().map(|&card| Card::from_u8(card)).collect()
+    }
+
+    pub fn community_cards(&self) -> Vec<Card> {
+        self.table.board().iter().map(|&card| Card::from_u8(card)).collect()
+    }
+
+    pub fn payout(&self, player_i: usize) -> f64 {
+        (self.table.stacks[player_i] - self.initial_chips[player_i]) as f64
     }
 
-    pub fn current_player(&self) -> Option<Rc<Player>> {
-        self.player.clone()
+    pub fn previous_state(&self) -> Option<&PokerGameState> {
+        self.previous_state.as_deref()
     }
 
-    // pub fn utility(&self, winner_i: Rc<Player>) -> Vec<f64> {
-    //     if self.is_terminal {
-    //         let mut utility = vec![-1.0; self.table.n_players()];
-    //         utility[winner_i] = 1.0;
-    //         utility
-    //     } else {
-    //         vec![0.0; self.table.n_players()]
-    //     }
-    // }
+    pub fn path_from_root(&self) -> Vec<&PokerGameState> {
+        // Every state of the hand so far, from the start of the hand to this
+        // one, for replaying it.
+        let mut path = vec![self];
+        while let Some(previous_state) = path.last().unwrap().previous_state() {
+            path.push(previous_state);
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn history(&self) -> &[Vec<Action>] {
+        &self.history.actions
+    }
+
+    pub fn betting_sequence(&self) -> String {
+        PokerGameState::encode_history(&self.history.actions)
+    }
+
+    pub fn abstract_betting_sequence(&self) -> String {
+        PokerGameState::encode_history(&self.history.abstract_actions)
+    }
+
+    pub fn info_set(&self) -> InfoSetKey {
+        self.info_set_of(self.player_i)
+    }
+
+    pub fn info_set_of(&self, player_i: usize) -> InfoSetKey {
+        self.info_set_with_cards(&self.table.private_cards[player_i])
+    }
+
+    pub fn info_set_with_cards(&self, private_cards: &[u8; 2]) -> InfoSetKey {
+        // The information set of a player holding `private_cards` here, which
+        // need not be the cards they were dealt.
+        let board = self.table.board();
+        match &self.card_abstraction {
+            Some(card_abstraction) => {
+                // The street the cards are on, which lags the betting stage
+                // while the next cards are still to be dealt.
+                let street = BettingStage::from_n_community_cards(board.len());
+                let cards: Vec<Card> = private_cards.iter().map(|&card| Card::from_u8(card)).collect();
+                let bucket = card_abstraction.bucket(street, &cards, &self.community_cards());
+                InfoSetKey::from_bucket(bucket, &self.history.betting_key)
+            }
+            None => InfoSetKey::from_cards(private_cards, board, &self.history.betting_key),
+        }
+    }
+
+    pub fn raise_action(&self, n_chips: i32) -> Action {
+        // A bet if nobody has bet yet this round, otherwise a raise.
+        if self.biggest_bet() > self.street_start_bet {
+            Action::RaiseTo(n_chips)
+        } else {
+            Action::Bet(n_chips)
+        }
+    }
+
+    fn child(&self) -> PokerGameState {
+        // A copy of this state to move on from, linked back to it. Both
+        // clones share the betting history until the child changes it.
+        let mut state = self.clone();
+        state.previous_state = Some(Arc::new(self.clone()));
+        state.action = None;
+        state.abstract_action = None;
+        state
+    }
+
+    fn deal(&self, cards: &[u8]) -> PokerGameState {
+        // The state after dealing `cards`: two to each player in seat order
+        // pre-flop, or the next community cards.
+        let mut state = self.child();
+        if state.betting_stage == BettingStage::PreFlop {
+            for (player_i, hand) in cards.chunks(2).enumerate() {
+                state.table.deal_private_cards(player_i, [hand[0], hand[1]]);
+            }
+            state.is_dealt = true;
+        } else {
+            for &card in cards {
+                state.table.deal_community_card(card);
+            }
+        }
+        state.start_betting_round();
+        state
+    }
+
+    fn private_card_deals(deck: &[u8], n_players: usize) -> Vec<Vec<u8>> {
+        if n_players == 0 {
+            return vec![Vec::new()];
+        }
+        let mut deals = Vec::new();
+        for hand in deck.iter().copied().combinations(2) {
+            let rest: Vec<u8> = deck.iter().copied().filter(|card| !hand.contains(card)).collect();
+            for others in PokerGameState::private_card_deals(&rest, n_players - 1) {
+                deals.push(hand.iter().copied().chain(others).collect());
+            }
+        }
+        deals
+    }
+
+    fn encode_history(history: &[Vec<Action>]) -> String {
+        // Each street's action codes, streets separated by '/'.
+        history
+            .iter()
+            .map(|actions| actions.iter().map(|action| action.code()).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    fn n_cards_to_deal(&self) -> usize {
+        if self.betting_stage == BettingStage::PreFlop {
+            if self.is_dealt { 0 } else { 2 * self.n_players() }
+        } else {
+            self.betting_stage.n_community_cards() - self.table.board().len()
+        }
+    }
+
+    fn biggest_bet(&self) -> i32 {
+        self.table.biggest_bet()
+    }
+
+    fn n_active_players(&self) -> usize {
+        (0..self.n_players()).filter(|&player_i| self.table.is_active(player_i)).count()
+    }
+
+    fn has_moves(&self, player_i: usize) -> bool {
+        self.table.is_active(player_i) && !self.table.is_all_in(player_i)
+    }
+
+    fn next_player_with_moves(&self, from: usize) -> usize {
+        let n_players = self.n_players();
+        (from..from + n_players)
+            .map(|player_i| player_i % n_players)
+            .find(|&player_i| self.has_moves(player_i))
+            .expect("No player left to act")
+    }
+
+    fn start_betting_round(&mut self) {
+        self.n_raises = 0;
+        self.min_raise = self.big_blind;
+        // The blinds are the first bets of the pre-flop round.
+        self.street_start_bet = if self.betting_stage == BettingStage::PreFlop { 0 } else { self.biggest_bet() };
+        self.can_raise = [true; MAX_PLAYERS];
+        let biggest_bet = self.biggest_bet();
+        let n_players_with_moves = (0..self.n_players()).filter(|&player_i| self.has_moves(player_i)).count();
+        let someone_must_call = (0..self.n_players())
+            .any(|player_i| self.has_moves(player_i) && self.table.bets[player_i] < biggest_bet);
+        if n_players_with_moves > 1 || someone_must_call {
+            self.n_players_to_act = n_players_with_moves;
+            // The blinds sit in seats 0 and 1, so pre-flop action starts after
+            // them and later streets start with the small blind. Heads up the
+            // small blind is the button, who acts first pre-flop and last after.
+            let first_player_i = if self.betting_stage == BettingStage::PreFlop {
+                2
+            } else if self.n_players() == 2 {
+                1
+            } else {
+                0
+            };
+            self.player_i = self.next_player_with_moves(first_player_i);
+        } else {
+            // Everyone left is all in, so run the board out without betting.
+            self.end_betting_round();
+        }
+    }
+
+    fn end_betting_round(&mut self) {
+        match self.betting_stage.next() {
+            Some(stage) => {
+                self.betting_stage = stage;
+                Arc::make_mut(&mut self.history).end_street();
+            }
+            None => self.settle(),
+        }
+    }
+
+    fn settle(&mut self) {
+        // Award each side pot to the best hands still in it, splitting ties
+        // and giving odd chips to the earliest seats.
+        let n_players = self.n_players();
+        let active: Vec<usize> = (0..n_players).filter(|&player_i| self.table.is_active(player_i)).collect();
+        let ranks: Vec<i32> = if active.len() > 1 {
+            let board = self.community_cards();
+            (0..n_players)
+                .map(|player_i| {
+                    if self.table.is_active(player_i) {
+                        self.evaluator.evaluate(&self.private_cards(player_i), &board)
+                    } else {
+                        i32::MAX
+                    }
+                })
+                .collect()
+        } else {
+            vec![0; n_players]
+        };
+
+        // Each side pot takes the smallest remaining contribution from every
+        // player who still has chips in the pot.
+        let mut remaining = self.table.bets;
+        while let Some(level) = remaining[..n_players].iter().copied().filter(|&n_chips| n_chips > 0).min() {
+            let contributors: Vec<usize> = (0..n_players).filter(|&player_i| remaining[player_i] > 0).collect();
+            for &player_i in contributors.iter() {
+                remaining[player_i] -= level;
+            }
+            let n_total = level * contributors.len() as i32;
+            let mut eligible: Vec<usize> = contributors
+                .into_iter()
+                .filter(|&player_i| self.table.is_active(player_i))
+                .collect();
+            if eligible.is_empty() {
+                eligible = active.clone();
+            }
+            let best_rank = eligible.iter().map(|&player_i| ranks[player_i]).min().unwrap();
+            let winners: Vec<usize> = eligible
+                .into_iter()
+                .filter(|&player_i| ranks[player_i] == best_rank)
+                .collect();
+            let n_per_player = n_total / winners.len() as i32;
+            let n_remainder = n_total - n_per_player * winners.len() as i32;
+            for (winner_i, &player_i) in winners.iter().enumerate() {
+                let bonus = if (winner_i as i32) < n_remainder { 1 } else { 0 };
+                self.table.stacks[player_i] += n_per_player + bonus;
+            }
+        }
+        self.table.bets = [0; MAX_PLAYERS];
+        self.is_terminal = true;
+    }
+}
+
+impl GameState for PokerGameState {
+    type Action = Action;
+    type InfoSet = InfoSetKey;
+
+    fn n_players(&self) -> usize {
+        PokerGameState::n_players(self)
+    }
+
+    fn is_terminal(&self) -> bool {
+        PokerGameState::is_terminal(self)
+    }
+
+    fn is_chance_node(&self) -> bool {
+        PokerGameState::is_chance_node(self)
+    }
+
+    fn player_i(&self) -> usize {
+        self.player_i
+    }
+
+    fn legal_actions(&self) -> Vec<Action> {
+        PokerGameState::legal_actions(self)
+    }
+
+    fn apply_action(&self, action: &Action) -> Self {
+        PokerGameState::apply_action(self, action)
+    }
+
+    fn sample_chance_outcome(&self) -> Self {
+        PokerGameState::sample_chance_outcome(self)
+    }
+
+    fn payout(&self, player_i: usize) -> f64 {
+        PokerGameState::payout(self, player_i)
+    }
+
+    fn info_set(&self) -> InfoSetKey {
+        PokerGameState::info_set(self)
+    }
 }
 
+impl ChanceOutcomes for PokerGameState {
+    fn chance_outcomes(&self) -> Vec<(Self, f64)> {
+        PokerGameState::chance_outcomes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heads_up() -> PokerGameState {
+        // Stacks of 1000 with blinds of 50 and 100, in the default action
+        // abstraction.
+        PokerGameState::new_hand(&[1000, 1000], &Deck::new(None, None), 50, 100)
+    }
+
+    fn play(state: PokerGameState, actions: &[Action]) -> PokerGameState {
+        // Deals whenever cards are due.
+        actions.iter().fold(state, |state, action| {
+            let state = if state.is_chance_node() { state.sample_chance_outcome() } else { state };
+            state.apply_action(action)
+        })
+    }
+
+    #[test]
+    fn betting_sequences_by_street() {
+        // The flop bet of 250 is off the tree and translates to a half pot
+        // bet of 300.
+        let actions = [
+            Action::RaiseTo(300),
+            Action::Call,
+            Action::Check,
+            Action::Bet(250),
+            Action::Call,
+            Action::Check,
+            Action::Check,
+        ];
+        let state = play(heads_up(), &actions);
+        assert_eq!(state.betting_sequence(), "r300c/kb250c/kk/");
+        assert_eq!(state.abstract_betting_sequence(), "r300c/kb300c/kk/");
+        assert_eq!(state.history().len(), 4);
+        assert_eq!(state.history()[1], [Action::Check, Action::Bet(250), Action::Call]);
+    }
+
+    #[test]
+    fn path_from_root_replays_the_hand() {
+        let actions = [Action::Call, Action::Check, Action::Bet(100), Action::RaiseTo(400), Action::Fold];
+        let state = play(heads_up(), &actions);
+        assert!(state.is_terminal());
+
+        let path = state.path_from_root();
+        assert!(path[0].previous_state().is_none());
+        assert!(std::ptr::eq(*path.last().unwrap(), &state));
+        // Chance nodes deal cards rather than act.
+        let path_actions: Vec<Action> = path.iter().filter_map(|state| state.action()).collect();
+        assert_eq!(path_actions, actions);
+        assert_eq!(path.iter().filter(|state| state.is_chance_node()).count(), 2);
+        for (previous, state) in path.iter().zip(&path[1..]) {
+            assert!(std::ptr::eq(state.previous_state().unwrap(), *previous));
+        }
+    }
+}
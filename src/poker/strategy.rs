@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+use super::actions::{Action, LegalActions};
+use super::card::Card;
+use super::info_set::InfoSetKey;
+use super::state::BettingStage;
+use crate::bytes::ByteReader;
+use crate::cfr::agent::Agent;
+use crate::cfr::mccfr::sample_action;
+
+/// What a player is allowed to see when it is their turn: the public state of
+/// the hand, their own private cards and the actions they can take. Per-player
+/// vectors are indexed by seat.
+pub struct Observation {
+    pub player_i: usize,
+    pub betting_stage: BettingStage,
+    pub private_cards: Vec<Card>,
+    pub community_cards: Vec<Card>,
+    pub stacks: Vec<i32>,
+    pub bets: Vec<i32>,
+    pub is_active: Vec<bool>,
+    pub n_chips_to_call: i32,
+    pub history: Vec<Vec<Action>>,
+    // The abstract actions the CFR trainer considers, and the full ranges
+    // the rules allow.
+    pub legal_actions: Vec<Action>,
+    pub legal_ranges: LegalActions,
+    // The information set key the CFR trainer uses for this decision.
+    pub info_set: InfoSetKey,
+}
+
+/// Decides the actions of a player. The action returned must be allowed by
+/// the observation's legal ranges, but need not be one of its abstract
+/// legal actions.
+pub trait Strategy {
+    fn choose_action(&self, observation: &Observation) -> Action;
+}
+
+/// Plays uniformly at random among the legal actions.
+pub struct RandomStrategy;
+
+impl Strategy for RandomStrategy {
+    fn choose_action(&self, observation: &Observation) -> Action {
+        *observation.legal_actions.choose(&mut thread_rng()).unwrap()
+    }
+}
+
+/// Checks or calls every bet, never folding or raising.
+pub struct CallStrategy;
+
+impl Strategy for CallStrategy {
+    fn choose_action(&self, observation: &Observation) -> Action {
+        if observation.legal_ranges.check { Action::Check } else { Action::Call }
+    }
+}
+
+/// Raises the minimum with a premium starting hand - a pair of tens or better,
+/// or ace-king or ace-queen - and otherwise checks when it can and folds to
+/// any bet.
+pub struct PremiumStrategy;
+
+impl PremiumStrategy {
+    pub fn is_premium(cards: &[Card]) -> bool {
+        let mut ranks: Vec<i32> = cards.iter().map(|card| card.rank_int()).collect();
+        ranks.sort();
+        match ranks[..] {
+            [low, high] if low == high => low >= 10,
+            [low, high] => high == 14 && low >= 12,
+            _ => false,
+        }
+    }
+}
+
+impl Strategy for PremiumStrategy {
+    fn choose_action(&self, observation: &Observation) -> Action {
+        let check_or_call = if observation.legal_ranges.check { Action::Check } else { Action::Call };
+        if PremiumStrategy::is_premium(&observation.private_cards) {
+            let raise = observation.legal_actions
+                .iter()
+                .find(|action| matches!(action, Action::Bet(_) | Action::RaiseTo(_) | Action::AllIn));
+            raise.copied().unwrap_or(check_or_call)
+        } else if observation.legal_ranges.fold {
+            Action::Fold
+        } else {
+            check_or_call
+        }
+    }
+}
+
+/// Samples actions from a blueprint: the average strategy of a CFR agent,
+/// keyed by information set. Information sets are built from the betting
+/// history translated onto the action abstraction, so bets the blueprint was
+/// not trained on are read as nearby abstract ones. Decisions still missing
+/// from the blueprint are played uniformly at random.
+///
+/// Blueprints are saved in a binary file, with each probability quantized to
+/// a byte:
+///                 +-------+---------+--------------+-------------------+
+///                 | magic | version | n_info_sets  | info set x n      |
+///                 +-------+---------+--------------+-------------------+
+///    1) magic = the bytes "PLBP", version = u32, n_info_sets = u64
+///    2) per info set, in key order = key length: u16, the key bytes,
+///       n_actions: u8, then a u8 per action of probability * 255
+///
+/// All integers are little endian. Loading scales each row back to sum to one.
+pub struct BlueprintStrategy {
+    strategy: HashMap<InfoSetKey, Vec<f64>>,
+}
+
+impl BlueprintStrategy {
+    pub const MAGIC: &'static [u8; 4] = b"PLBP";
+    pub const VERSION: u32 = 1;
+
+    pub fn new(strategy: HashMap<InfoSetKey, Vec<f64>>) -> Self {
+        BlueprintStrategy { strategy }
+    }
+
+    pub fn probabilities(&self, observation: &Observation) -> Vec<f64> {
+        self.action_probabilities(&observation.info_set, observation.legal_actions.len())
+    }
+
+    pub fn action_probabilities(&self, info_set: &InfoSetKey, n_actions: usize) -> Vec<f64> {
+        // The blueprint's probability of each of the `n_actions` legal actions
+        // at `info_set`, uniform where the blueprint has no entry.
+        match self.strategy.get(info_set) {
+            Some(sigma) if sigma.len() == n_actions => sigma.clone(),
+            _ => vec![1.0 / n_actions as f64; n_actions],
+        }
+    }
+
+    pub fn from_agent(agent: &Agent<InfoSetKey>) -> Self {
+        let strategy = agent.strategy
+            .iter()
+            .map(|(info_set, strategy_sum)| {
+                (info_set.clone(), agent.average_strategy(info_set, strategy_sum.len()))
+            })
+            .collect();
+        BlueprintStrategy { strategy }
+    }
+
+    pub fn get(&self, info_set: &InfoSetKey) -> Option<&[f64]> {
+        self.strategy.get(info_set).map(|sigma| sigma.as_slice())
+    }
+
+    pub fn n_info_sets(&self) -> usize {
+        self.strategy.len()
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut info_sets: Vec<&InfoSetKey> = self.strategy.keys().collect();
+        info_sets.sort();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(info_sets.len() as u64).to_le_bytes());
+        for info_set in info_sets {
+            let key = info_set.as_bytes();
+            let sigma = &self.strategy[info_set];
+            if key.len() > u16::MAX as usize || sigma.len() > u8::MAX as usize {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("Info set {:?} is too large to save", info_set)));
+            }
+            bytes.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(key);
+            bytes.push(sigma.len() as u8);
+            bytes.extend(sigma.iter().map(|p| (p * 255.0).round() as u8));
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: &str) -> Result<BlueprintStrategy> {
+        let bytes = fs::read(path)?;
+        let mut reader = ByteReader::new(&bytes, "Blueprint");
+        reader.expect_header(Self::MAGIC, Self::VERSION)?;
+        // Every info set takes at least three bytes, which bounds how much a
+        // corrupt count can make us preallocate.
+        let n_info_sets = reader.u64()? as usize;
+        let mut strategy = HashMap::with_capacity(n_info_sets.min(reader.n_remaining() / 3));
+        for _ in 0..n_info_sets {
+            let key_len = reader.u16()? as usize;
+            let info_set = InfoSetKey::from_bytes(reader.take(key_len)?);
+            let n_actions = reader.u8()? as usize;
+            let quantized = reader.take(n_actions)?;
+            // Rounding leaves the bytes summing to about 255, so rescale
+            // rather than divide by 255. A row rounded to all zeros is
+            // played uniformly.
+            let total: f64 = quantized.iter().map(|&q| q as f64).sum();
+            let sigma = if total > 0.0 {
+                quantized.iter().map(|&q| q as f64 / total).collect()
+            } else {
+                vec![1.0 / n_actions as f64; n_actions]
+            };
+            strategy.insert(info_set, sigma);
+        }
+        if !reader.is_at_end() {
+            return Err(Error::new(ErrorKind::InvalidData, "Unexpected bytes after the last info set"));
+        }
+        Ok(BlueprintStrategy { strategy })
+    }
+}
+
+impl Strategy for BlueprintStrategy {
+    fn choose_action(&self, observation: &Observation) -> Action {
+        observation.legal_actions[sample_action(&self.probabilities(observation))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytes::temp_path;
+    use crate::cfr::trainer::{Trainer, TrainerConfig};
+    use crate::poker::toy_games::ToyGame;
+
+    fn kuhn_blueprint() -> BlueprintStrategy {
+        let root = ToyGame::kuhn().root();
+        let mut trainer = Trainer::new(TrainerConfig {
+            n_iterations: 1000,
+            print_iteration: 0,
+            ..Default::default()
+        });
+        trainer.train(|| root.clone());
+        BlueprintStrategy::from_agent(&trainer.agent)
+    }
+
+    fn load_error(bytes: &[u8], name: &str) -> Option<ErrorKind> {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        let result = BlueprintStrategy::load(&path);
+        fs::remove_file(&path).unwrap();
+        result.err().map(|error| error.kind())
+    }
+
+    #[test]
+    fn blueprint_round_trip() {
+        // Probabilities are quantized to bytes, so they come back within half
+        // a step of 1/255, and saving what was loaded gives the same file.
+        let blueprint = kuhn_blueprint();
+        let path = temp_path("blueprint-round-trip");
+        blueprint.save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let loaded = BlueprintStrategy::load(&path).unwrap();
+        loaded.save(&path).unwrap();
+        let bytes_again = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.n_info_sets(), blueprint.n_info_sets());
+        for (info_set, sigma) in &blueprint.strategy {
+            let loaded_sigma = loaded.get(info_set).unwrap();
+            assert_eq!(loaded_sigma.len(), sigma.len());
+            for (p, loaded_p) in sigma.iter().zip(loaded_sigma) {
+                assert!((p - loaded_p).abs() < 0.01, "{:?}: {:?} loaded as {:?}", info_set, sigma, loaded_sigma);
+            }
+        }
+        assert_eq!(bytes_again, bytes);
+    }
+
+    #[test]
+    fn blueprint_rejects_corrupt_files() {
+        let path = temp_path("blueprint-corrupt");
+        kuhn_blueprint().save(&path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[..4].copy_from_slice(b"PLCK");
+        assert_eq!(load_error(&wrong_magic, "blueprint-magic"), Some(ErrorKind::InvalidData));
+        let mut wrong_version = bytes.clone();
+        wrong_version[4..8].copy_from_slice(&(BlueprintStrategy::VERSION + 1).to_le_bytes());
+        assert_eq!(load_error(&wrong_version, "blueprint-version"), Some(ErrorKind::InvalidData));
+        assert_eq!(load_error(&bytes[..bytes.len() - 1], "blueprint-truncated"), Some(ErrorKind::InvalidData));
+        let mut extra_bytes = bytes.clone();
+        extra_bytes.push(0);
+        assert_eq!(load_error(&extra_bytes, "blueprint-extra"), Some(ErrorKind::InvalidData));
+        let mut huge_count = bytes;
+        huge_count[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(load_error(&huge_count, "blueprint-count"), Some(ErrorKind::InvalidData));
+    }
+}
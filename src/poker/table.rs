@@ -0,0 +1,126 @@
+use rand::{thread_rng, Rng};
+
+use super::card::Card;
+use super::deck::Deck;
+
+pub const MAX_PLAYERS: usize = 9;
+
+/// The chips and cards of one hand as plain values: stacks, the chips each
+/// seat has put in the pot this hand, who has folded, and cards as bytes from
+/// `Card::to_u8`, with the deck as a bit set of the cards not yet dealt. It is
+/// `Copy`, so copying it into a new game state is a plain memory copy, and
+/// every update is a few array writes.
+///
+/// Seats past `n_players` are unused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Table {
+    n_players: usize,
+    pub stacks: [i32; MAX_PLAYERS],
+    // Chips put in the pot this hand, which together make up the pot.
+    pub bets: [i32; MAX_PLAYERS],
+    pub is_folded: [bool; MAX_PLAYERS],
+    pub private_cards: [[u8; 2]; MAX_PLAYERS],
+    board: [u8; 5],
+    n_board_cards: usize,
+    deck: u64,
+}
+
+impl Table {
+    pub fn new(stacks: &[i32], deck: &Deck) -> Self {
+        if stacks.len() < 2 || stacks.len() > MAX_PLAYERS {
+            panic!("A table seats 2 to {} players, not {}", MAX_PLAYERS, stacks.len());
+        }
+        let mut table = Table {
+            n_players: stacks.len(),
+            stacks: [0; MAX_PLAYERS],
+            bets: [0; MAX_PLAYERS],
+            is_folded: [false; MAX_PLAYERS],
+            private_cards: [[0; 2]; MAX_PLAYERS],
+            board: [0; 5],
+            n_board_cards: 0,
+            deck: Table::deck_mask(deck),
+        };
+        table.stacks[..stacks.len()].copy_from_slice(stacks);
+        table
+    }
+
+    pub fn deck_mask(deck: &Deck) -> u64 {
+        Card::to_mask(&deck.all_cards())
+    }
+
+    pub fn n_players(&self) -> usize {
+        self.n_players
+    }
+
+    pub fn pot(&self) -> i32 {
+        self.bets.iter().sum()
+    }
+
+    pub fn biggest_bet(&self) -> i32 {
+        self.bets.iter().copied().max().unwrap_or_default()
+    }
+
+    pub fn is_active(&self, player_i: usize) -> bool {
+        !self.is_folded[player_i]
+    }
+
+    pub fn is_all_in(&self, player_i: usize) -> bool {
+        self.is_active(player_i) && self.stacks[player_i] == 0
+    }
+
+    pub fn add_to_pot(&mut self, player_i: usize, n_chips: i32) -> i32 {
+        // Moves up to `n_chips` from the player's stack into the pot, all of
+        // the stack if it is too short, and returns how many moved.
+        if n_chips < 0 {
+            panic!("Can not subtract chips from pot.")
+        }
+        let n_chips = n_chips.min(self.stacks[player_i]);
+        self.stacks[player_i] -= n_chips;
+        self.bets[player_i] += n_chips;
+        n_chips
+    }
+
+    pub fn board(&self) -> &[u8] {
+        &self.board[..self.n_board_cards]
+    }
+
+    pub fn deal_card(&mut self) -> u8 {
+        // A uniformly random card of those left in the deck.
+        let n_cards = self.deck.count_ones();
+        if n_cards == 0 {
+            panic!("Deck is empty");
+        }
+        let mut deck = self.deck;
+        for _ in 0..thread_rng().gen_range(0..n_cards) {
+            deck &= deck - 1;
+        }
+        let card = deck.trailing_zeros() as u8;
+        self.remove_from_deck(card);
+        card
+    }
+
+    pub fn deal_private_cards(&mut self, player_i: usize, cards: [u8; 2]) {
+        self.private_cards[player_i] = cards;
+        self.remove_from_deck(cards[0]);
+        self.remove_from_deck(cards[1]);
+    }
+
+    pub fn deal_community_card(&mut self, card: u8) {
+        self.board[self.n_board_cards] = card;
+        self.n_board_cards += 1;
+        self.remove_from_deck(card);
+    }
+
+    pub fn deck(&self) -> u64 {
+        // The cards left in the deck.
+        self.deck
+    }
+
+    pub fn remove_from_deck(&mut self, card: u8) {
+        self.deck &= !(1 << card);
+    }
+
+    pub fn set_deck(&mut self, deck: u64) {
+        self.deck = deck;
+    }
+}
@@ -0,0 +1,367 @@
+/// Kuhn poker and Leduc hold'em, small games with known equilibria for
+/// checking the solvers. Both are one card poker for two players, so one
+/// state type plays either, set up by a `ToyGame`:
+///
+/// - Kuhn poker: a jack, queen and king, an ante of 1 and one round in which
+///   a single bet of 1 is allowed. The first player's equilibrium value is
+///   -1/18.
+/// - Leduc hold'em: two suits of jack, queen and king, an ante of 1 and two
+///   rounds, with a community card dealt before the second. Each round allows
+///   a bet and a raise, of 2 in the first round and 4 in the second. A card
+///   pairing the board wins, otherwise the higher card. The first player's
+///   equilibrium value is about -0.0856.
+///
+/// A trainer whose average strategy's exploitability, from
+/// `cfr::best_response`, falls towards zero and whose first player value
+/// approaches `game_value` is solving these games correctly.
+///
+/// These games are not played through `PokerGameState`, which is hold'em:
+/// two private cards, blinds, four streets and no-limit raises sized by an
+/// action abstraction. One private card, antes, fixed bet sizes and a single
+/// community card would each need a mode of their own in its betting and
+/// dealing. Instead `ToyGameState` implements the same `GameState` and
+/// `ChanceOutcomes` traits with the same `Action` and `InfoSetKey` types, so
+/// the trainers and best response run unchanged on it. The no-limit state
+/// machine is tested in `actions` and `state`, and solved end to end on a
+/// push or fold hold'em game in the tests below.
+use rand::{thread_rng, Rng};
+
+use super::actions::Action;
+use super::deck::Deck;
+use super::info_set::InfoSetKey;
+use super::table::Table;
+use crate::cfr::game_state::{ChanceOutcomes, GameState};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToyGame {
+    // The cards of the deck, as a bit set of `Card::to_u8` bytes.
+    pub deck: u64,
+    pub ante: i32,
+    pub n_rounds: usize,
+    pub bet_sizes: [i32; 2],
+    // Bets and raises allowed per round.
+    pub max_raises: u32,
+    // The first player's value at equilibrium.
+    pub game_value: f64,
+}
+
+impl ToyGame {
+    pub fn kuhn() -> Self {
+        let deck = Deck::new(Some(vec!["spades"]), Some(vec![11, 12, 13]));
+        ToyGame {
+            deck: Table::deck_mask(&deck),
+            ante: 1,
+            n_rounds: 1,
+            bet_sizes: [1, 1],
+            max_raises: 1,
+            game_value: -1.0 / 18.0,
+        }
+    }
+
+    pub fn leduc() -> Self {
+        let deck = Deck::new(Some(vec!["spades", "hearts"]), Some(vec![11, 12, 13]));
+        ToyGame {
+            deck: Table::deck_mask(&deck),
+            ante: 1,
+            n_rounds: 2,
+            bet_sizes: [2, 4],
+            max_raises: 2,
+            game_value: -0.0856,
+        }
+    }
+
+    pub fn root(&self) -> ToyGameState {
+        ToyGameState {
+            game: *self,
+            private_cards: None,
+            board: None,
+            round: 0,
+            player_i: 0,
+            bets: [self.ante; 2],
+            round_start_bet: self.ante,
+            n_raises: 0,
+            n_actions: 0,
+            folded: None,
+            is_terminal: false,
+            betting_key: Vec::new(),
+        }
+    }
+}
+
+/// One node of a hand of Kuhn poker or Leduc hold'em. Actions are the ones of
+/// no-limit hold'em, with bet and raise amounts as totals for the round.
+#[derive(Clone, Debug)]
+pub struct ToyGameState {
+    game: ToyGame,
+    private_cards: Option<[u8; 2]>,
+    board: Option<u8>,
+    round: usize,
+    player_i: usize,
+    // Chips put in the pot this hand, antes included.
+    bets: [i32; 2],
+    round_start_bet: i32,
+    n_raises: u32,
+    n_actions: usize,
+    folded: Option<usize>,
+    is_terminal: bool,
+    betting_key: Vec<u8>,
+}
+
+impl ToyGameState {
+    pub fn private_cards(&self) -> Option<[u8; 2]> {
+        self.private_cards
+    }
+
+    pub fn board(&self) -> Option<u8> {
+        self.board
+    }
+
+    fn deal(&self, cards: &[u8]) -> ToyGameState {
+        // Two private cards, then the community card.
+        let mut state = self.clone();
+        match cards {
+            [card_0, card_1] => state.private_cards = Some([*card_0, *card_1]),
+            [card] => state.board = Some(*card),
+            _ => panic!("Can not deal {} cards", cards.len()),
+        }
+        state
+    }
+
+    fn deck(&self) -> Vec<u8> {
+        // The cards not dealt yet.
+        let dealt: Vec<u8> = self.private_cards.iter().flatten().chain(self.board.iter()).copied().collect();
+        (0..64)
+            .filter(|&card| self.game.deck & 1 << card != 0 && !dealt.contains(&card))
+            .collect()
+    }
+
+    fn strength(&self, player_i: usize) -> u8 {
+        // A pair with the board beats any single card, otherwise the rank
+        // decides. Cards are rank-major, so rank is the byte over four.
+        let rank = self.private_cards.unwrap()[player_i] / 4;
+        match self.board {
+            Some(board) if board / 4 == rank => u8::MAX,
+            _ => rank,
+        }
+    }
+
+    fn end_round(&mut self) {
+        if self.round + 1 < self.game.n_rounds {
+            self.round += 1;
+            self.player_i = 0;
+            self.round_start_bet = self.bets[0];
+            self.n_raises = 0;
+            self.n_actions = 0;
+            self.betting_key.push(InfoSetKey::STREET_END);
+        } else {
+            self.is_terminal = true;
+        }
+    }
+}
+
+impl GameState for ToyGameState {
+    type Action = Action;
+    type InfoSet = InfoSetKey;
+
+    fn n_players(&self) -> usize {
+        2
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_terminal
+    }
+
+    fn is_chance_node(&self) -> bool {
+        !self.is_terminal && (self.private_cards.is_none() || (self.round > 0 && self.board.is_none()))
+    }
+
+    fn player_i(&self) -> usize {
+        self.player_i
+    }
+
+    fn legal_actions(&self) -> Vec<Action> {
+        let opponent_bet = self.bets[1 - self.player_i];
+        let can_raise = self.n_raises < self.game.max_raises;
+        let raise_to = opponent_bet - self.round_start_bet + self.game.bet_sizes[self.round];
+        if self.bets[self.player_i] < opponent_bet {
+            let mut actions = vec![Action::Fold, Action::Call];
+            if can_raise {
+                actions.push(Action::RaiseTo(raise_to));
+            }
+            actions
+        } else if can_raise {
+            vec![Action::Check, Action::Bet(raise_to)]
+        } else {
+            vec![Action::Check]
+        }
+    }
+
+    fn apply_action(&self, action: &Action) -> Self {
+        let legal_actions = self.legal_actions();
+        let action_i = legal_actions
+            .iter()
+            .position(|legal_action| legal_action == action)
+            .unwrap_or_else(|| panic!("Illegal action '{}'", action));
+        let mut state = self.clone();
+        state.betting_key.push(action_i as u8);
+        state.n_actions += 1;
+        let player_i = self.player_i;
+        match *action {
+            Action::Fold => {
+                state.folded = Some(player_i);
+                state.is_terminal = true;
+                return state;
+            }
+            Action::Check => {}
+            Action::Call => state.bets[player_i] = state.bets[1 - player_i],
+            Action::Bet(n_chips) | Action::RaiseTo(n_chips) => {
+                state.bets[player_i] = state.round_start_bet + n_chips;
+                state.n_raises += 1;
+            }
+            Action::AllIn => panic!("Illegal action '{}'", action),
+        }
+        // A round ends with a call, or when both players check.
+        let is_even = state.bets[0] == state.bets[1];
+        if is_even && (*action == Action::Call || state.n_actions == 2) {
+            state.end_round();
+        } else {
+            state.player_i = 1 - player_i;
+        }
+        state
+    }
+
+    fn sample_chance_outcome(&self) -> Self {
+        let mut deck = self.deck();
+        let n_cards = if self.private_cards.is_none() { 2 } else { 1 };
+        let cards: Vec<u8> = (0..n_cards)
+            .map(|_| deck.swap_remove(thread_rng().gen_range(0..deck.len())))
+            .collect();
+        self.deal(&cards)
+    }
+
+    fn payout(&self, player_i: usize) -> f64 {
+        let opponent_i = 1 - player_i;
+        let winner_i = match self.folded {
+            Some(folded_i) => Some(1 - folded_i),
+            None => match self.strength(player_i).cmp(&self.strength(opponent_i)) {
+                std::cmp::Ordering::Greater => Some(player_i),
+                std::cmp::Ordering::Less => Some(opponent_i),
+                std::cmp::Ordering::Equal => None,
+            },
+        };
+        match winner_i {
+            Some(winner_i) if winner_i == player_i => self.bets[opponent_i] as f64,
+            Some(_) => -self.bets[player_i] as f64,
+            None => 0.0,
+        }
+    }
+
+    fn info_set(&self) -> InfoSetKey {
+        let hand = [self.private_cards.unwrap()[self.player_i]];
+        let board: Vec<u8> = self.board.into_iter().collect();
+        InfoSetKey::from_cards(&hand, &board, &self.betting_key)
+    }
+}
+
+impl ChanceOutcomes for ToyGameState {
+    fn chance_outcomes(&self) -> Vec<(Self, f64)> {
+        let deck = self.deck();
+        let deals: Vec<Vec<u8>> = if self.private_cards.is_none() {
+            deck.iter()
+                .flat_map(|&card_0| deck.iter().filter(move |&&card_1| card_1 != card_0).map(move |&card_1| vec![card_0, card_1]))
+                .collect()
+        } else {
+            deck.iter().map(|&card| vec![card]).collect()
+        };
+        let probability = 1.0 / deals.len() as f64;
+        deals.iter().map(|cards| (self.deal(cards), probability)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cfr::best_response::{exploitability, Exploitability};
+    use crate::cfr::trainer::{Trainer, TrainerConfig};
+    use crate::poker::action_abstraction::ActionAbstraction;
+    use crate::poker::state::PokerGameState;
+
+    fn train(game: ToyGame, n_iterations: usize) -> Exploitability {
+        let root = game.root();
+        let mut trainer = Trainer::new(TrainerConfig {
+            n_iterations,
+            print_iteration: 0,
+            ..Default::default()
+        });
+        trainer.train(|| root.clone());
+        exploitability(&root, |state: &ToyGameState| {
+            trainer.agent.average_strategy(&state.info_set(), state.legal_actions().len())
+        })
+    }
+
+    fn kuhn_equilibrium(state: &ToyGameState) -> Vec<f64> {
+        // The equilibrium in which the first player never bets a jack, so
+        // never bets at all, and calls a bet with a queen a third of the time.
+        // Ranks from `Card::to_u8` are 9 for a jack, 10 for a queen and 11
+        // for a king.
+        let rank = state.private_cards.unwrap()[state.player_i] / 4;
+        match (&state.betting_key[..], rank) {
+            ([], _) => vec![1.0, 0.0],
+            ([0], 9) => vec![2.0 / 3.0, 1.0 / 3.0],
+            ([0], 10) => vec![1.0, 0.0],
+            ([0], _) => vec![0.0, 1.0],
+            ([1], 9) | ([0, 1], 9) => vec![1.0, 0.0],
+            ([1], 10) | ([0, 1], 10) => vec![2.0 / 3.0, 1.0 / 3.0],
+            ([1], _) | ([0, 1], _) => vec![0.0, 1.0],
+            (betting_key, _) => panic!("Kuhn poker has no betting {:?}", betting_key),
+        }
+    }
+
+    #[test]
+    fn kuhn_equilibrium_is_unexploitable() {
+        let game = ToyGame::kuhn();
+        let result = exploitability(&game.root(), kuhn_equilibrium);
+        assert!(result.exploitability().abs() < 1e-9, "exploitability {}", result.exploitability());
+        assert!((result.policy_values[0] - game.game_value).abs() < 1e-9);
+    }
+
+    #[test]
+    fn trainer_solves_kuhn() {
+        let game = ToyGame::kuhn();
+        let result = train(game, 20_000);
+        assert!(result.exploitability() < 0.02, "exploitability {}", result.exploitability());
+        assert!((result.policy_values[0] - game.game_value).abs() < 0.01, "value {}", result.policy_values[0]);
+    }
+
+    #[test]
+    fn trainer_solves_push_or_fold_hold_em() {
+        // The no-limit state machine under the same trainer and best
+        // response: heads up with stacks of 5 big blinds, a nine card deck
+        // and all in as the only raise, before the flop.
+        let deck = Deck::new(Some(vec!["spades"]), Some((6..=14).collect()));
+        let mut root = PokerGameState::new_hand(&[10, 10], &deck, 1, 2);
+        root.set_action_abstraction(Arc::new(ActionAbstraction::parse("preflop 0 allin").unwrap()));
+
+        let mut trainer = Trainer::new(TrainerConfig {
+            n_iterations: 10_000,
+            print_iteration: 0,
+            ..Default::default()
+        });
+        trainer.train(|| root.clone());
+        let result = exploitability(&root, |state: &PokerGameState| {
+            trainer.agent.average_strategy(&state.info_set(), GameState::legal_actions(state).len())
+        });
+        // Playing uniformly at random is exploitable for about 1.55.
+        assert!(result.exploitability() < 0.2, "exploitability {}", result.exploitability());
+    }
+
+    #[test]
+    fn trainer_solves_leduc() {
+        let game = ToyGame::leduc();
+        let result = train(game, 50_000);
+        assert!(result.exploitability() < 0.2, "exploitability {}", result.exploitability());
+        assert!((result.policy_values[0] - game.game_value).abs() < 0.05, "value {}", result.policy_values[0]);
+    }
+}